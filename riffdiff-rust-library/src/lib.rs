@@ -1,9 +1,13 @@
 use anyhow::Context;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use memmap2::Mmap;
 use rayon::prelude::*;
 use std::collections::BinaryHeap;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, IoSlice, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::path::{Path, PathBuf};
 use pyo3::prelude::*;
@@ -18,7 +22,120 @@ pub struct CompatEntry {
     pub idx2: u32,
 }
 
+/// Bitset-backed compatibility lookup. For every `(set_i, idx_a)` it stores a
+/// packed `u64` bitmask — one block per other set `j` — whose set bits are the
+/// indices of set `j` compatible with `idx_a`. This replaces the dense
+/// `n_sets² · max_set_size²` boolean map with about `Σlen · Σlen` bits (each
+/// per-set block rounded up to whole `u64` words), and lets
+/// backtracking prune with whole-word AND/popcount instead of re-checking every
+/// previously chosen residue against every candidate.
+pub struct CompatMasks {
+    n_sets: usize,
+    set_lengths: Vec<usize>,
+    words_per_set: Vec<usize>,
+    set_word_offset: Vec<usize>,
+    total_words: usize,
+    /// `masks[i][a]` is one `total_words`-word block holding the per-set
+    /// compatibility masks for `(i, a)`.
+    masks: Vec<Vec<Box<[u64]>>>,
+}
+
+#[inline]
+fn words_for(len: usize) -> usize {
+    len.div_ceil(64)
+}
+
+#[inline]
+fn any_bits_set(mask: &[u64]) -> bool {
+    mask.iter().any(|&w| w != 0)
+}
+
+impl CompatMasks {
+    pub fn build(compat: &[CompatEntry], set_lengths: &[u32]) -> CompatMasks {
+        let n_sets = set_lengths.len();
+        let set_lengths: Vec<usize> = set_lengths.iter().map(|&l| l as usize).collect();
+        let words_per_set: Vec<usize> = set_lengths.iter().map(|&l| words_for(l)).collect();
+
+        let mut set_word_offset = vec![0usize; n_sets];
+        let mut acc = 0usize;
+        for j in 0..n_sets {
+            set_word_offset[j] = acc;
+            acc += words_per_set[j];
+        }
+        let total_words = acc;
+
+        let mut masks: Vec<Vec<Box<[u64]>>> = (0..n_sets)
+            .map(|i| {
+                (0..set_lengths[i])
+                    .map(|_| vec![0u64; total_words].into_boxed_slice())
+                    .collect()
+            })
+            .collect();
+
+        let set_bit = |block: &mut [u64], set_j: usize, bit: usize| {
+            let word = set_word_offset[set_j] + bit / 64;
+            block[word] |= 1u64 << (bit % 64);
+        };
+
+        for entry in compat {
+            let (i, j, a, b) = (
+                entry.set1 as usize,
+                entry.set2 as usize,
+                entry.idx1 as usize,
+                entry.idx2 as usize,
+            );
+            set_bit(&mut masks[i][a], j, b);
+            set_bit(&mut masks[j][b], i, a); // symmetric
+        }
+
+        CompatMasks {
+            n_sets,
+            set_lengths,
+            words_per_set,
+            set_word_offset,
+            total_words,
+            masks,
+        }
+    }
+
+    /// The compatibility mask of `(set_i, idx_a)` restricted to set `set_j`.
+    #[inline]
+    fn submask(&self, set_i: usize, idx_a: usize, set_j: usize) -> &[u64] {
+        let block = &self.masks[set_i][idx_a];
+        let start = self.set_word_offset[set_j];
+        &block[start..start + self.words_per_set[set_j]]
+    }
+
+    /// Initial per-set feasibility: every valid index is still reachable.
+    fn full_feasible(&self) -> Vec<Vec<u64>> {
+        (0..self.n_sets)
+            .map(|j| {
+                let mut words = vec![0u64; self.words_per_set[j]];
+                for bit in 0..self.set_lengths[j] {
+                    words[bit / 64] |= 1u64 << (bit % 64);
+                }
+                words
+            })
+            .collect()
+    }
+}
+
+/// Visit every index set in `mask`, yielding it to `f`. Iterates set bits with
+/// `trailing_zeros`, replacing a linear `0..len` scan.
+#[inline]
+fn for_each_set_bit(mask: &[u64], mut f: impl FnMut(usize)) {
+    for (word_i, &word) in mask.iter().enumerate() {
+        let mut w = word;
+        while w != 0 {
+            let bit = w.trailing_zeros() as usize;
+            f(word_i * 64 + bit);
+            w &= w - 1; // clear lowest set bit
+        }
+    }
+}
+
 #[pyfunction]
+#[pyo3(signature = (combo_file, score_files, n_combos, n_sets, top_n, pair_files=vec![]))]
 fn find_top_combos(
     py: Python,
     combo_file: String,
@@ -26,6 +143,7 @@ fn find_top_combos(
     n_combos: usize,
     n_sets: usize,
     top_n: usize,
+    pair_files: Vec<(usize, usize, String)>,
 ) -> PyResult<Py<PyArray2<u16>>> {
     let combo_path = Path::new(&combo_file);
     let score_paths: Vec<PathBuf> = score_files.into_iter().map(PathBuf::from).collect();
@@ -47,7 +165,20 @@ fn find_top_combos(
             Err(e) => return Err(PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string())),
         }
     }
-    let score_set = RotamerScoreSet { scores };
+
+    // Load any pairwise interaction matrices. Each unordered pair is supplied
+    // once; the matrix is indexed in the same `(i, j)` order as its key, so the
+    // energy lookup is `pair[i][j][r_i][r_j]`.
+    let mut pairs = std::collections::HashMap::new();
+    for (i, j, path) in &pair_files {
+        let matrix = match load_f32_pair_matrix_from_csv(Path::new(path)) {
+            Ok(m) => m,
+            Err(e) => return Err(PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string())),
+        };
+        pairs.insert((*i, *j), matrix);
+    }
+
+    let score_set = RotamerScoreSet { scores, pairs };
 
     // Score and find the best
     let best = score_combinations(&combos, &score_set, top_n);
@@ -67,9 +198,62 @@ fn find_top_combos(
     Ok(array.to_owned().into())
 }
 
+/// Fused alternative to [`find_top_combos`]: instead of scoring a pre-enumerated
+/// combo file, run branch-and-bound directly over the compatibility masks and
+/// return the top-N by score. `compat_entries` are `(set1, set2, idx1, idx2)`
+/// tuples; the same symmetric-compatibility convention as the enumeration FFI
+/// applies. The returned array has the same `[n, n_sets]` `u16` shape as
+/// `find_top_combos`, where `n` is at most `top_n`.
+#[pyfunction]
+fn find_top_combos_bnb(
+    py: Python,
+    compat_entries: Vec<(u32, u32, u32, u32)>,
+    set_lengths: Vec<u32>,
+    score_files: Vec<String>,
+    top_n: usize,
+) -> PyResult<Py<PyArray2<u16>>> {
+    let compat: Vec<CompatEntry> = compat_entries
+        .into_iter()
+        .map(|(set1, set2, idx1, idx2)| CompatEntry {
+            set1,
+            set2,
+            idx1,
+            idx2,
+        })
+        .collect();
+    let masks = CompatMasks::build(&compat, &set_lengths);
+    let n_sets = set_lengths.len();
+
+    let mut scores = Vec::new();
+    for path in &score_files {
+        match load_f32_score_array_from_csv(Path::new(path)) {
+            Ok(s) => scores.push(s),
+            Err(e) => return Err(PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string())),
+        }
+    }
+    let score_set = RotamerScoreSet {
+        scores,
+        pairs: std::collections::HashMap::new(),
+    };
+
+    let best = branch_and_bound_top_n(&masks, &score_set, top_n);
+
+    let mut flat_result: Vec<u16> = Vec::with_capacity(best.len() * n_sets);
+    for ScoredCombo { combo, .. } in &best {
+        flat_result.extend_from_slice(combo);
+    }
+
+    let array = flat_result
+        .to_pyarray(py)
+        .reshape([best.len(), n_sets])
+        .map_err(|e: pyo3::PyErr| e)?;
+    Ok(array.to_owned().into())
+}
+
 #[pymodule]
 fn riffdiff_rust_library(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(find_top_combos, m)?)?;
+    m.add_function(wrap_pyfunction!(find_top_combos_bnb, m)?)?;
     Ok(())
 }
 
@@ -81,85 +265,106 @@ pub extern "C" fn generate_valid_combinations_to_file(
     set_lengths_ptr: *const u32,
     set_lengths_len: usize,
     output_path_ptr: *const libc::c_char,
+    compressed: libc::c_int,
 ) {
     let compat_slice = unsafe { std::slice::from_raw_parts(compat_ptr, compat_len) };
     let set_lengths = unsafe { std::slice::from_raw_parts(set_lengths_ptr, set_lengths_len) };
     let output_path = unsafe { std::ffi::CStr::from_ptr(output_path_ptr).to_str().unwrap() };
 
     let n_sets = set_lengths.len();
-    let max_set_size = *set_lengths.iter().max().unwrap_or(&0) as usize;
-
-    let mut compat_map = vec![vec![vec![vec![false; max_set_size]; max_set_size]; n_sets]; n_sets];
-    for i in 0..n_sets {
-        for j in 0..n_sets {
-            compat_map[i][j] = vec![vec![false; max_set_size]; max_set_size];
-        }
-    }
-
-    for entry in compat_slice {
-        let (i, j, a, b) = (
-            entry.set1 as usize,
-            entry.set2 as usize,
-            entry.idx1 as usize,
-            entry.idx2 as usize,
-        );
-        compat_map[i][j][a][b] = true;
-        compat_map[j][i][b][a] = true;
-    }
-
 
-    let writer = Arc::new(Mutex::new(BinWriter::new(output_path).expect("Unable to create BinWriter")));
+    let compat = Arc::new(CompatMasks::build(compat_slice, set_lengths));
+
+    // The writer is internally synchronised, so a bare `Arc` suffices; workers
+    // batch rows in thread-local buffers and only take the writer lock on flush.
+    // `compressed != 0` streams rows through zlib instead of writing them raw;
+    // callers trade the ability to mmap the combo file for a smaller one on
+    // disk, which matters on the huge enumeration runs this entry point feeds.
+    let writer = Arc::new(if compressed != 0 {
+        BinWriter::new_compressed(output_path, DeflateMode::Fast)
+            .expect("Unable to create compressed BinWriter")
+    } else {
+        BinWriter::new(output_path).expect("Unable to create BinWriter")
+    });
 
-    let compat_map = Arc::new(compat_map);
-    let set_lengths = Arc::new(set_lengths.to_vec());
+    // Seed feasibility once; each worker clones it and narrows the masks as it
+    // descends.
+    let initial = compat.full_feasible();
 
     // Top-level parallelism: depth = 0
     (0..set_lengths[0]).into_par_iter().for_each(|first_idx| {
         let mut combo = vec![first_idx];
-        recurse_write(
-            1,
-            &set_lengths,
-            &compat_map,
-            &mut combo,
-            &writer,
-        );
+        let mut buffer = RowBuffer::new();
+
+        // Narrow the remaining sets' feasibility by the first choice, pruning
+        // immediately if any future set is already unreachable.
+        let mut feasible = initial.clone();
+        if narrow(&compat, &mut feasible, 0, first_idx as usize, n_sets) {
+            recurse_write(1, &compat, &mut combo, &feasible, &writer, &mut buffer);
+        }
+
+        // Drain whatever this task accumulated below the flush threshold.
+        writer
+            .flush_buffer(&mut buffer)
+            .expect("Unable to flush combo buffer");
     });
-    writer.lock().unwrap()
+    writer
         .close_with_metadata(format!("{}.meta", output_path), n_sets)
         .expect("Unable to write metadata");
 
 }
 
+/// AND every unassigned set's running feasibility with the mask of choosing
+/// `idx` in `set`. Returns `false` (prune) the moment any future set becomes
+/// all-zero — no completion can extend this partial assignment.
+fn narrow(
+    compat: &CompatMasks,
+    feasible: &mut [Vec<u64>],
+    set: usize,
+    idx: usize,
+    n_sets: usize,
+) -> bool {
+    for j in (set + 1)..n_sets {
+        let mask = compat.submask(set, idx, j);
+        let target = &mut feasible[j];
+        for (t, &m) in target.iter_mut().zip(mask.iter()) {
+            *t &= m;
+        }
+        if !any_bits_set(target) {
+            return false;
+        }
+    }
+    true
+}
+
 fn recurse_write(
     depth: usize,
-    set_lengths: &[u32],
-    compat_map: &Vec<Vec<Vec<Vec<bool>>>>,
+    compat: &CompatMasks,
     current_combo: &mut Vec<u32>,
-    writer: &Arc<Mutex<BinWriter>>,
+    feasible: &[Vec<u64>],
+    writer: &Arc<BinWriter>,
+    buffer: &mut RowBuffer,
 ) {
-    let n_sets = set_lengths.len();
+    let n_sets = compat.n_sets;
     if depth == n_sets {
         //let json_line = json!(current_combo);
-        let w = writer.lock().unwrap();
-        w.write_line(current_combo).expect("Unable to write line");
+        writer
+            .write_line_buffered(buffer, current_combo)
+            .expect("Unable to write line");
         return;
     }
 
-    for idx in 0..set_lengths[depth] {
-        let mut is_valid = true;
-        for prev_set in 0..depth {
-            let prev_idx = current_combo[prev_set] as usize;
-            if !compat_map[prev_set][depth][prev_idx][idx as usize] {
-                is_valid = false;
-                break;
-            }
-        }
-        if is_valid {
-            current_combo.push(idx);
-            recurse_write(depth + 1, set_lengths, compat_map, current_combo, writer);
+    // Candidates for this depth are exactly the still-feasible bits — no
+    // per-candidate re-check against earlier choices is needed.
+    let candidates = feasible[depth].clone();
+    for_each_set_bit(&candidates, |idx| {
+        let mut child = feasible.to_vec();
+        if narrow(compat, &mut child, depth, idx, n_sets) {
+            current_combo.push(idx as u32);
+            recurse_write(depth + 1, compat, current_combo, &child, writer, buffer);
             current_combo.pop();
         }
-    }
+    });
 }
 
 #[unsafe(no_mangle)]
@@ -174,76 +379,224 @@ pub extern "C" fn generate_valid_combinations(
     let set_lengths = unsafe { std::slice::from_raw_parts(set_lengths_ptr, set_lengths_len) };
     let n_sets = set_lengths.len();
 
-    // Build compatibility map
-    let mut compat_map = vec![vec![vec![vec![false; 0]; 0]; n_sets]; n_sets];
-    let max_set_size = *set_lengths.iter().max().unwrap_or(&0) as usize;
-    for i in 0..n_sets {
-        for j in 0..n_sets {
-            compat_map[i][j] = vec![vec![false; max_set_size]; max_set_size];
-        }
-    }
-    for entry in compat_slice {
-        let (i, j, a, b) = (
-            entry.set1 as usize,
-            entry.set2 as usize,
-            entry.idx1 as usize,
-            entry.idx2 as usize,
-        );
-        compat_map[i][j][a][b] = true;
-        compat_map[j][i][b][a] = true; // Symmetric
-    }
+    let compat = CompatMasks::build(compat_slice, set_lengths);
+    let initial = compat.full_feasible();
 
     // Parallelize the first level of recursion
     let first_set_size = set_lengths[0] as usize;
 
     (0..first_set_size).into_par_iter().for_each(|idx| {
         let mut combo = vec![idx as u32];
-        backtrack(
-            1,
-            n_sets,
-            set_lengths,
-            &compat_map,
-            &mut combo,
-            output_callback,
-        );
+        let mut feasible = initial.clone();
+        if narrow(&compat, &mut feasible, 0, idx, n_sets) {
+            backtrack(1, &compat, &mut combo, &feasible, output_callback);
+        }
     });
 }
 
-fn backtrack(depth: usize, n_sets: usize, set_lengths: &[u32], compat_map: &Vec<Vec<Vec<Vec<bool>>>>, current_combo: &mut Vec<u32>, output_callback: extern "C" fn(*const u32, usize)) {
+fn backtrack(
+    depth: usize,
+    compat: &CompatMasks,
+    current_combo: &mut Vec<u32>,
+    feasible: &[Vec<u64>],
+    output_callback: extern "C" fn(*const u32, usize),
+) {
+    let n_sets = compat.n_sets;
     if depth == n_sets {
         output_callback(current_combo.as_ptr(), current_combo.len());
-        
+
         return;
     }
-    for idx in 0..set_lengths[depth] {
-        let mut valid = true;
-        for prev_set in 0..depth {
-            let prev_idx = current_combo[prev_set] as usize;
-            let cur_idx = idx as usize;
-            if !compat_map[prev_set][depth][prev_idx][cur_idx] {
-                valid = false;
-                break;
+    let candidates = feasible[depth].clone();
+    for_each_set_bit(&candidates, |idx| {
+        let mut child = feasible.to_vec();
+        if narrow(compat, &mut child, depth, idx, n_sets) {
+            current_combo.push(idx as u32);
+            backtrack(depth + 1, compat, current_combo, &child, output_callback);
+            current_combo.pop();
+        }
+    });
+}
+
+/// Streaming-compression effort for [`BinWriter`]. `Fast` favours throughput on
+/// huge enumeration runs; `Best` favours a smaller file.
+#[derive(Debug, Clone, Copy)]
+pub enum DeflateMode {
+    Fast,
+    Best,
+}
+
+impl DeflateMode {
+    fn level(self) -> Compression {
+        match self {
+            DeflateMode::Fast => Compression::fast(),
+            DeflateMode::Best => Compression::best(),
+        }
+    }
+}
+
+/// The underlying byte sink: either raw (mmap-able on read) or a streaming zlib
+/// encoder that compresses each row incrementally so the full combo set is
+/// never held in memory.
+enum Sink {
+    Raw(BufWriter<File>),
+    Zlib(ZlibEncoder<BufWriter<File>>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Sink::Raw(w) => w.write(buf),
+            Sink::Zlib(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::Raw(w) => w.flush(),
+            Sink::Zlib(w) => w.flush(),
+        }
+    }
+}
+
+/// Write all of `bufs` via repeated vectored writes. Mirrors the still-unstable
+/// `Write::write_all_vectored`: on a short write it advances past the consumed
+/// slices/offset and retries with the remainder.
+fn write_all_vectored<W: Write>(writer: &mut W, bufs: &[IoSlice<'_>]) -> std::io::Result<()> {
+    let mut idx = 0;
+    let mut offset = 0;
+    while idx < bufs.len() {
+        let mut staging: Vec<IoSlice> = Vec::with_capacity(bufs.len() - idx);
+        staging.push(IoSlice::new(&bufs[idx][offset..]));
+        for b in &bufs[idx + 1..] {
+            staging.push(IoSlice::new(b));
+        }
+        let mut written = writer.write_vectored(&staging)?;
+        if written == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                "failed to write whole buffer",
+            ));
+        }
+        while written > 0 {
+            let remaining = bufs[idx].len() - offset;
+            if written >= remaining {
+                written -= remaining;
+                idx += 1;
+                offset = 0;
+            } else {
+                offset += written;
+                written = 0;
             }
         }
-        if valid {
-            current_combo.push(idx);
-            backtrack(depth + 1, n_sets, set_lengths, compat_map, current_combo, output_callback);
-            current_combo.pop();
+    }
+    Ok(())
+}
+
+/// A per-thread staging area for completed combinations. Each worker appends
+/// rows here and only touches the shared [`BinWriter`] lock once the buffer
+/// crosses [`RowBuffer::FLUSH_THRESHOLD`], turning one lock acquisition per
+/// combination into one per few-MiB batch.
+pub struct RowBuffer {
+    rows: Vec<Box<[u8]>>,
+    bytes: usize,
+}
+
+impl RowBuffer {
+    /// Flush once a batch reaches a few MiB of row bytes.
+    const FLUSH_THRESHOLD: usize = 4 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        RowBuffer {
+            rows: Vec::new(),
+            bytes: 0,
+        }
+    }
+
+    fn push(&mut self, combo: &[u32]) {
+        let mut row = Vec::with_capacity(combo.len() * 2);
+        for &value in combo {
+            row.extend_from_slice(&(value as u16).to_le_bytes());
         }
+        self.bytes += row.len();
+        self.rows.push(row.into_boxed_slice());
+    }
+
+    fn should_flush(&self) -> bool {
+        self.bytes >= Self::FLUSH_THRESHOLD
+    }
+}
+
+impl Default for RowBuffer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub struct BinWriter {
-    writer: Arc<Mutex<BufWriter<File>>>,
-    line_count: Arc<Mutex<usize>>,
+    writer: Arc<Mutex<Sink>>,
+    line_count: Arc<AtomicUsize>,
+    compressed: bool,
 }
 
 impl BinWriter {
     pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
         let file = File::create(path)?;
-        let writer = Arc::new(Mutex::new(BufWriter::new(file)));
-        let line_count = Arc::new(Mutex::new(0));
-        Ok(BinWriter { writer, line_count })
+        let writer = Arc::new(Mutex::new(Sink::Raw(BufWriter::new(file))));
+        let line_count = Arc::new(AtomicUsize::new(0));
+        Ok(BinWriter {
+            writer,
+            line_count,
+            compressed: false,
+        })
+    }
+
+    /// Like [`BinWriter::new`] but streams rows through a zlib encoder (the zlib
+    /// header is emitted on the first write). Reads go through the inflate path
+    /// in [`load_u16_mmap`].
+    pub fn new_compressed<P: AsRef<Path>>(path: P, mode: DeflateMode) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        let encoder = ZlibEncoder::new(BufWriter::new(file), mode.level());
+        let writer = Arc::new(Mutex::new(Sink::Zlib(encoder)));
+        let line_count = Arc::new(AtomicUsize::new(0));
+        Ok(BinWriter {
+            writer,
+            line_count,
+            compressed: true,
+        })
+    }
+
+    /// Stage a single completed combination into `buffer`, flushing to the
+    /// shared sink only when the batch is large enough to amortise the lock.
+    pub fn write_line_buffered(
+        &self,
+        buffer: &mut RowBuffer,
+        line: &[u32],
+    ) -> std::io::Result<()> {
+        buffer.push(line);
+        if buffer.should_flush() {
+            self.flush_buffer(buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Write every staged row to the sink under a single lock acquisition using
+    /// a vectored write, then reset the buffer. Safe to call with an empty
+    /// buffer (used for the final drain at the end of a task).
+    pub fn flush_buffer(&self, buffer: &mut RowBuffer) -> std::io::Result<()> {
+        if buffer.rows.is_empty() {
+            return Ok(());
+        }
+        let slices: Vec<IoSlice> = buffer.rows.iter().map(|r| IoSlice::new(r)).collect();
+        {
+            let mut writer = self.writer.lock().unwrap();
+            write_all_vectored(&mut *writer, &slices)?;
+        }
+        self.line_count
+            .fetch_add(buffer.rows.len(), Ordering::Relaxed);
+        buffer.rows.clear();
+        buffer.bytes = 0;
+        Ok(())
     }
 
     pub fn write_line(&self, line: &[u32]) -> std::io::Result<()> {
@@ -254,25 +607,72 @@ impl BinWriter {
             writer.write_all(&val_u16.to_le_bytes())?;
         }
         //writer.flush()?;
-        *self.line_count.lock().unwrap() += 1;
+        self.line_count.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
 
     pub fn close_with_metadata<P: AsRef<Path>>(&self, metadata_path: P, row_len: usize) -> std::io::Result<()> {
-        let lines = *self.line_count.lock().unwrap();
-        // Explicitly flush the buffer
-        self.writer.lock().unwrap().flush()?;
+        let lines = self.line_count.load(Ordering::Relaxed);
+        // Finish the stream: for the zlib path this flushes the final deflate
+        // block and writes the Adler-32 checksum.
+        {
+            let mut sink = self.writer.lock().unwrap();
+            if let Sink::Zlib(encoder) = &mut *sink {
+                encoder.try_finish()?;
+            }
+            sink.flush()?;
+        }
 
-        // Write metadata
+        // Write metadata. The row/col counts are always of the *uncompressed*
+        // matrix so readers can size their buffers regardless of encoding.
+        let compression = if self.compressed { "zlib" } else { "none" };
         let mut metadata_file = File::create(metadata_path)?;
-        let metadata = format!("{{\"rows\": {}, \"cols\": {}, \"dtype\": \"u16\"}}", lines, row_len);
+        let metadata = format!(
+            "{{\"rows\": {}, \"cols\": {}, \"dtype\": \"u16\", \"compression\": \"{}\"}}",
+            lines, row_len, compression
+        );
         metadata_file.write_all(metadata.as_bytes())?;
         Ok(())
     }
 }
 
+/// A dense two-body score matrix for an ordered set pair `(i, j)`, indexed by
+/// `(idx_i, idx_j)` and stored row-major.
+struct PairMatrix {
+    cols: usize,
+    data: Vec<f32>,
+}
+
+impl PairMatrix {
+    #[inline]
+    fn get(&self, idx_i: usize, idx_j: usize) -> f32 {
+        self.data[idx_i * self.cols + idx_j]
+    }
+}
+
 struct RotamerScoreSet {
-    scores: Vec<Arc<[f32]>>
+    scores: Vec<Arc<[f32]>>,
+    /// Optional pairwise interaction energies keyed by `(i, j)` with `i < j`.
+    /// Empty for an additive-only model.
+    pairs: std::collections::HashMap<(usize, usize), PairMatrix>,
+}
+
+impl RotamerScoreSet {
+    /// Singleton + two-body energy of a combination: `Σ singleton[i][r_i] +
+    /// Σ_{i<j} pair[i][j][r_i][r_j]`, before normalization.
+    fn combo_energy(&self, combo: &[u16]) -> f32 {
+        let mut total: f32 = combo
+            .iter()
+            .enumerate()
+            .map(|(i, &r)| self.scores[i][r as usize])
+            .sum();
+
+        for (&(i, j), matrix) in &self.pairs {
+            total += matrix.get(combo[i] as usize, combo[j] as usize);
+        }
+
+        total
+    }
 }
 
 pub struct ValidComboMatrix {
@@ -290,7 +690,10 @@ impl ValidComboMatrix {
 #[derive(Debug, PartialEq)]
 struct ScoredCombo {
     score: f32,
-    index: usize
+    index: usize,
+    /// The chosen rotamer index per set. Populated directly by the fused
+    /// branch-and-bound search, which has no backing combo file to index into.
+    combo: Vec<u16>,
 }
 
 impl Eq for ScoredCombo {}
@@ -319,31 +722,193 @@ fn score_combinations(
 
     (0..combos.n_combos).into_par_iter().for_each(|i| {
         let combo_indices = combos.get_combo(i);
-        let score_sum: f32 = combo_indices
-            .iter()
-            .enumerate()
-            .map(|(residue_i, &rotamer_index)| scores.scores[residue_i][rotamer_index as usize])
-            .sum();
+        let score_sum = scores.combo_energy(combo_indices);
 
         let avg_score = score_sum / combos.n_sets as f32;
         
         let mut heap_guard = heap.lock().unwrap();
         if heap_guard.len() < top_n {
-            heap_guard.push(ScoredCombo { score: avg_score, index: i });
+            heap_guard.push(ScoredCombo {
+                score: avg_score,
+                index: i,
+                combo: combo_indices.to_vec(),
+            });
         } else if avg_score > heap_guard.peek().unwrap().score {
             heap_guard.pop();
-            heap_guard.push(ScoredCombo { score: avg_score, index: i });
+            heap_guard.push(ScoredCombo {
+                score: avg_score,
+                index: i,
+                combo: combo_indices.to_vec(),
+            });
         }
     });
 
     heap.into_inner().unwrap().into_sorted_vec()
 }
 
+/// A partial assignment on the branch-and-bound frontier: the indices chosen so
+/// far, the running per-set feasibility, the summed singleton score of the
+/// chosen residues, and an optimistic upper bound on any completion.
+struct PartialNode {
+    depth: usize,
+    combo: Vec<u16>,
+    feasible: Vec<Vec<u64>>,
+    score_sum: f32,
+    bound: f32,
+}
+
+impl PartialEq for PartialNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl Eq for PartialNode {}
+
+impl PartialOrd for PartialNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PartialNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Max-heap on the optimistic bound: always expand the most promising
+        // frontier node first.
+        self.bound
+            .partial_cmp(&other.bound)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+/// Fused top-N search by branch-and-bound, producing the same `ScoredCombo`
+/// ranking as enumerating every valid combination and calling
+/// [`score_combinations`], but skipping the vast majority of the tree.
+///
+/// Each frontier node's bound is `(sum of chosen singleton scores) + (sum over
+/// every unassigned set of that set's maximum singleton score)`. The per-set
+/// maxima are precomputed once, so evaluating a bound is O(remaining sets).
+/// Because the bound can only over-estimate a completion, once the best
+/// remaining node's bound is no better than the current N-th best complete
+/// score, no unexpanded branch can win and the search stops.
+fn branch_and_bound_top_n(
+    compat: &CompatMasks,
+    scores: &RotamerScoreSet,
+    top_n: usize,
+) -> Vec<ScoredCombo> {
+    let n_sets = compat.n_sets;
+    if n_sets == 0 || top_n == 0 {
+        return Vec::new();
+    }
+
+    // Precompute each set's best singleton score and the suffix sums of those
+    // maxima, so a node at `depth` adds `suffix_max[depth]` in O(1).
+    let per_set_max: Vec<f32> = (0..n_sets)
+        .map(|i| {
+            (0..compat.set_lengths[i])
+                .map(|r| scores.scores[i][r])
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+        .collect();
+    let mut suffix_max = vec![0.0f32; n_sets + 1];
+    for d in (0..n_sets).rev() {
+        suffix_max[d] = suffix_max[d + 1] + per_set_max[d];
+    }
+
+    let norm = n_sets as f32;
+
+    let mut frontier: BinaryHeap<PartialNode> = BinaryHeap::new();
+    frontier.push(PartialNode {
+        depth: 0,
+        combo: Vec::new(),
+        feasible: compat.full_feasible(),
+        score_sum: 0.0,
+        bound: suffix_max[0],
+    });
+
+    // Completed combos kept as a size-`top_n` min-heap (smallest score on top),
+    // reusing `ScoredCombo`'s reversed ordering.
+    let mut results: BinaryHeap<ScoredCombo> = BinaryHeap::with_capacity(top_n + 1);
+
+    while let Some(node) = frontier.pop() {
+        // Every remaining frontier node has bound <= node.bound. If that can no
+        // longer beat the N-th best complete score, we are done.
+        if results.len() == top_n {
+            let nth_best = results.peek().unwrap().score;
+            if node.bound / norm <= nth_best {
+                break;
+            }
+        }
+
+        if node.depth == n_sets {
+            let avg = node.score_sum / norm;
+            if results.len() < top_n {
+                results.push(ScoredCombo {
+                    score: avg,
+                    index: 0,
+                    combo: node.combo,
+                });
+            } else if avg > results.peek().unwrap().score {
+                results.pop();
+                results.push(ScoredCombo {
+                    score: avg,
+                    index: 0,
+                    combo: node.combo,
+                });
+            }
+            continue;
+        }
+
+        let depth = node.depth;
+        let candidates = node.feasible[depth].clone();
+        for_each_set_bit(&candidates, |idx| {
+            let mut child_feasible = node.feasible.clone();
+            if narrow(compat, &mut child_feasible, depth, idx, n_sets) {
+                let score_sum = node.score_sum + scores.scores[depth][idx];
+                let mut combo = node.combo.clone();
+                combo.push(idx as u16);
+                frontier.push(PartialNode {
+                    depth: depth + 1,
+                    combo,
+                    feasible: child_feasible,
+                    score_sum,
+                    bound: score_sum + suffix_max[depth + 1],
+                });
+            }
+        });
+    }
+
+    results.into_sorted_vec()
+}
+
+/// Reads the `"compression"` field `close_with_metadata` wrote next to the
+/// combo file, rather than guessing the encoding from the data's leading
+/// bytes — a raw `u16` stream can legitimately start with a zlib-looking
+/// header, so sniffing is not safe.
+fn is_zlib_encoded(combo_path: &Path) -> anyhow::Result<bool> {
+    let meta_path = format!("{}.meta", combo_path.to_string_lossy());
+    let meta = std::fs::read_to_string(&meta_path)
+        .with_context(|| format!("Failed to read combo metadata: {}", meta_path))?;
+    Ok(meta.contains("\"compression\": \"zlib\""))
+}
+
 fn load_u16_mmap(path: &Path) -> anyhow::Result<Arc<[u16]>> {
     let file = File::open(path).context("Failed to open combo mmap")?;
     let mmap = unsafe { Mmap::map(&file).context("Failed to mmap file")? };
-    let data: &[u16] = bytemuck::cast_slice(&mmap[..]);
-    Ok(Arc::from(data))
+
+    if is_zlib_encoded(path)? {
+        // Stream-inflate into an owned buffer; we cannot mmap a compressed file.
+        let mut decoder = ZlibDecoder::new(&mmap[..]);
+        let mut bytes = Vec::new();
+        decoder
+            .read_to_end(&mut bytes)
+            .context("Failed to inflate compressed combo file")?;
+        let data: &[u16] = bytemuck::cast_slice(&bytes[..]);
+        Ok(Arc::from(data))
+    } else {
+        let data: &[u16] = bytemuck::cast_slice(&mmap[..]);
+        Ok(Arc::from(data))
+    }
 }
 
 use std::io::{BufReader};
@@ -370,4 +935,42 @@ pub fn load_f32_score_array_from_csv(path: &Path) -> Result<Arc<[f32]>> {
     }
 
     Ok(Arc::from(scores))
-}
\ No newline at end of file
+}
+
+/// Loads a dense pairwise energy matrix from a headerless CSV: row `idx_i`,
+/// column `idx_j`, cell is the interaction energy between those two
+/// rotamers. Every row must have the same column count.
+fn load_f32_pair_matrix_from_csv(path: &Path) -> Result<PairMatrix> {
+    let file = File::open(path).with_context(|| "Failed to open pair energy CSV")?;
+    let reader = BufReader::new(file);
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(reader);
+
+    let mut data = Vec::new();
+    let mut cols = None;
+
+    for (i, result) in rdr.records().enumerate() {
+        let record = result.with_context(|| format!("Error parsing CSV line {}", i + 1))?;
+        let row_cols = *cols.get_or_insert(record.len());
+        if record.len() != row_cols {
+            anyhow::bail!(
+                "Ragged pair energy matrix: line {} has {} columns, expected {}",
+                i + 1,
+                record.len(),
+                row_cols
+            );
+        }
+        for (j, field) in record.iter().enumerate() {
+            let value: f32 = field
+                .parse()
+                .with_context(|| format!("Invalid pair energy '{}' at line {} col {}", field, i + 1, j + 1))?;
+            data.push(value);
+        }
+    }
+
+    Ok(PairMatrix {
+        cols: cols.unwrap_or(0),
+        data,
+    })
+}
@@ -7,11 +7,37 @@ use std::path::{Path, PathBuf};
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub rfdiffusion_script: Option<String>,
+    pub rfdiffusion_python: Option<String>,
     pub ligandmpnn_script: Option<String>,
+    pub ligandmpnn_python: Option<String>,
     pub rosetta_bin: Option<String>,
     pub esmfold_python: Option<String>,
     pub protein_edits_scripts_dir: Option<String>,
+    pub protein_edits_python: Option<String>,
     pub python_path: String,
+
+    /// Job execution backend: `"local"` (default) or `"slurm"`.
+    #[serde(default = "default_job_backend")]
+    pub job_backend: String,
+    /// SLURM partition to submit array jobs to.
+    pub slurm_partition: Option<String>,
+    /// CPU cores requested per array task.
+    #[serde(default = "default_cores")]
+    pub slurm_cores: usize,
+    /// Memory requested per array task, e.g. `"8G"`.
+    pub slurm_memory: Option<String>,
+    /// Cap on concurrently running array tasks.
+    pub slurm_max_array_tasks: Option<usize>,
+    /// Maximum concurrent local child processes (default: CPU count).
+    pub max_parallel: Option<usize>,
+}
+
+fn default_job_backend() -> String {
+    "local".to_string()
+}
+
+fn default_cores() -> usize {
+    1
 }
 
 impl Config {
@@ -31,11 +57,20 @@ impl Config {
             // Default empty config if no file provided - relying largely on envs or defaults
             Config {
                 rfdiffusion_script: None,
+                rfdiffusion_python: None,
                 ligandmpnn_script: None,
+                ligandmpnn_python: None,
                 rosetta_bin: None,
                 esmfold_python: None,
                 protein_edits_scripts_dir: None,
+                protein_edits_python: None,
                 python_path: "python".to_string(), // Default python
+                job_backend: default_job_backend(),
+                slurm_partition: None,
+                slurm_cores: default_cores(),
+                slurm_memory: None,
+                slurm_max_array_tasks: None,
+                max_parallel: None,
             }
         };
 
@@ -43,9 +78,15 @@ impl Config {
         if let Ok(val) = env::var("RFDIFFUSION_SCRIPT") {
             config.rfdiffusion_script = Some(val);
         }
+        if let Ok(val) = env::var("RFDIFFUSION_PYTHON_PATH") {
+            config.rfdiffusion_python = Some(val);
+        }
         if let Ok(val) = env::var("LIGANDMPNN_SCRIPT") {
             config.ligandmpnn_script = Some(val);
         }
+        if let Ok(val) = env::var("LIGANDMPNN_PYTHON_PATH") {
+            config.ligandmpnn_python = Some(val);
+        }
         if let Ok(val) = env::var("ROSETTA_BIN_PATH") {
             config.rosetta_bin = Some(val);
         }
@@ -55,9 +96,18 @@ impl Config {
         if let Ok(val) = env::var("AUXILIARY_RUNNER_SCRIPTS_DIR") {
             config.protein_edits_scripts_dir = Some(val);
         }
+        if let Ok(val) = env::var("PROTEIN_EDITS_PYTHON_PATH") {
+            config.protein_edits_python = Some(val);
+        }
         if let Ok(val) = env::var("PROTFLOW_PYTHON") {
             config.python_path = val;
         }
+        if let Ok(val) = env::var("JOB_BACKEND") {
+            config.job_backend = val;
+        }
+        if let Ok(val) = env::var("SLURM_PARTITION") {
+            config.slurm_partition = Some(val);
+        }
 
         Ok(config)
     }
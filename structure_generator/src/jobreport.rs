@@ -0,0 +1,125 @@
+use crate::poses::PoseRecord;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Lifecycle state of a pipeline stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A persisted record of a single runner stage, written as `{prefix}_report.json`
+/// under `work_dir`. The embedded pose snapshot lets a stopped pipeline resume
+/// without recomputing finished stages; see [`crate::pipeline::run_pipeline`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobReport {
+    pub id: Uuid,
+    pub stage_prefix: String,
+    pub status: JobStatus,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub input_pose_count: usize,
+    pub output_pose_count: usize,
+    pub poses: Vec<PoseRecord>,
+}
+
+impl JobReport {
+    /// Path of the report for `prefix` under `work_dir`.
+    pub fn report_path(work_dir: &Path, prefix: &str) -> PathBuf {
+        work_dir.join(format!("{}_report.json", prefix))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open report: {:?}", path))?;
+        let report = serde_json::from_reader(BufReader::new(file))?;
+        Ok(report)
+    }
+
+    pub fn save(&self, work_dir: &Path) -> Result<()> {
+        let path = Self::report_path(work_dir, &self.stage_prefix);
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create report: {:?}", path))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Load the report for `prefix` only if it exists and is `Completed`.
+    pub fn load_if_complete(work_dir: &Path, prefix: &str) -> Result<Option<Self>> {
+        let path = Self::report_path(work_dir, prefix);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let report = Self::load(&path)?;
+        if report.status == JobStatus::Completed {
+            Ok(Some(report))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Builder populated by the pipeline driver as a stage progresses, mirroring
+/// the builder-pattern job/report design used in larger pipeline engines.
+pub struct JobReportBuilder {
+    id: Uuid,
+    stage_prefix: String,
+    status: JobStatus,
+    start_time: Option<DateTime<Utc>>,
+    input_pose_count: usize,
+}
+
+impl JobReportBuilder {
+    pub fn new(stage_prefix: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            stage_prefix: stage_prefix.to_string(),
+            status: JobStatus::Queued,
+            start_time: None,
+            input_pose_count: 0,
+        }
+    }
+
+    /// Mark the stage as running with its input pose count.
+    pub fn start(mut self, input_pose_count: usize) -> Self {
+        self.status = JobStatus::Running;
+        self.start_time = Some(Utc::now());
+        self.input_pose_count = input_pose_count;
+        self
+    }
+
+    /// Finish successfully, snapshotting the stage's output poses.
+    pub fn complete(self, poses: &[PoseRecord]) -> JobReport {
+        JobReport {
+            id: self.id,
+            stage_prefix: self.stage_prefix,
+            status: JobStatus::Completed,
+            start_time: self.start_time,
+            end_time: Some(Utc::now()),
+            input_pose_count: self.input_pose_count,
+            output_pose_count: poses.len(),
+            poses: poses.to_vec(),
+        }
+    }
+
+    /// Finish as failed, preserving whatever poses were produced so far.
+    pub fn fail(self, poses: &[PoseRecord]) -> JobReport {
+        JobReport {
+            id: self.id,
+            stage_prefix: self.stage_prefix,
+            status: JobStatus::Failed,
+            start_time: self.start_time,
+            end_time: Some(Utc::now()),
+            input_pose_count: self.input_pose_count,
+            output_pose_count: poses.len(),
+            poses: poses.to_vec(),
+        }
+    }
+}
@@ -1,11 +1,18 @@
 mod config;
+mod jobreport;
+mod pipeline;
 mod poses;
+mod provenance;
 mod runners;
 mod tools;
 
 use crate::config::Config;
 use crate::poses::Poses;
-use crate::runners::Runner;
+use crate::provenance::Ledger;
+use crate::runners::{
+    JobOptions, JobPool, JobStarter, LocalJobStarter, Runner, SlurmJobStarter,
+};
+use std::sync::Arc;
 use crate::tools::{
     esmfold::ESMFold,
     ligandmpnn::LigandMPNN,
@@ -66,6 +73,64 @@ enum Commands {
         #[arg(long)]
         ref_pdb: String,
     },
+    /// Run a declarative pipeline spec (JSON/TOML/YAML) of ordered steps.
+    Run {
+        #[arg(long)]
+        pipeline: PathBuf,
+    },
+    /// Re-execute a recorded provenance ledger and verify output hashes match.
+    Replay {
+        #[arg(long)]
+        provenance: PathBuf,
+    },
+    /// Pareto-front filter the loaded poses across several score columns.
+    /// Each objective is `column` (lower is better) or `column:high` (higher
+    /// is better), e.g. `--objectives total_score,plddt:high`.
+    FilterPareto {
+        #[arg(long, value_delimiter = ',', num_args = 1..)]
+        objectives: Vec<String>,
+        #[arg(long, default_value_t = 1)]
+        keep_fronts: usize,
+        #[arg(long)]
+        out_json: Option<PathBuf>,
+    },
+    /// Combine several score columns into a single weighted, z-normalized
+    /// column. Each term is `column:weight` (lower is better) or
+    /// `column:weight:high` (higher is better), e.g.
+    /// `--terms total_score:1.0,plddt:0.5:high`.
+    CompositeScore {
+        #[arg(long)]
+        out_col: String,
+        #[arg(long, value_delimiter = ',', num_args = 1..)]
+        terms: Vec<String>,
+        #[arg(long)]
+        out_json: Option<PathBuf>,
+    },
+}
+
+/// Build the execution backend selected in the config. The local backend
+/// records a provenance ledger at `{work_dir}/provenance.jsonl`.
+fn build_job_starter(config: &Config, work_dir: &std::path::Path) -> Box<dyn JobStarter> {
+    match config.job_backend.as_str() {
+        "slurm" => {
+            let opts = JobOptions {
+                partition: config.slurm_partition.clone(),
+                cores: config.slurm_cores,
+                memory: config.slurm_memory.clone(),
+                max_array_tasks: config.slurm_max_array_tasks,
+                ..JobOptions::default()
+            };
+            Box::new(SlurmJobStarter::new(opts))
+        }
+        _ => {
+            let ledger = Arc::new(Ledger::new(work_dir.join("provenance.jsonl")));
+            let pool = match config.max_parallel {
+                Some(n) => JobPool::new(n),
+                None => JobPool::default(),
+            };
+            Box::new(LocalJobStarter::with_pool(Some(ledger), pool))
+        }
+    }
 }
 
 #[tokio::main]
@@ -92,6 +157,10 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::load(args.config.as_ref())?;
     info!("Configuration loaded.");
 
+    // Select the execution backend for all runners.
+    let job_starter = build_job_starter(&config, &work_dir);
+    let job_starter = job_starter.as_ref();
+
     let mut poses = Poses::new(&work_dir);
 
     // Load input if provided
@@ -119,7 +188,7 @@ async fn main() -> anyhow::Result<()> {
                 .unwrap_or(&config.python_path);
             let mut rfdiff = RFDiffusion::new(rfdiff_python, rfdiff_canon.to_str().unwrap());
             rfdiff.num_diffusions = num_diffusions;
-            rfdiff.run(&mut poses, "screening_rfdiffusion").await?;
+            rfdiff.run(&mut poses, "screening_rfdiffusion", job_starter).await?;
 
             // 2. ChainAdder (renumber/copy ref)
             let aux_scripts = config
@@ -132,7 +201,7 @@ async fn main() -> anyhow::Result<()> {
                 .unwrap_or(&config.python_path);
 
             let adder = ChainAdder::new(ed_python, aux_scripts, "Z");
-            adder.run(&mut poses, "screening_chain_adder").await?;
+            adder.run(&mut poses, "screening_chain_adder", job_starter).await?;
 
             // 3. LigandMPNN
             let mpnn_script = config
@@ -146,7 +215,7 @@ async fn main() -> anyhow::Result<()> {
                 .unwrap_or(&config.python_path);
             let mut mpnn = LigandMPNN::new(mpnn_python, mpnn_canon.to_str().unwrap());
             mpnn.nseq = 1; // Default for screening
-            mpnn.run(&mut poses, "screening_mpnn").await?;
+            mpnn.run(&mut poses, "screening_mpnn", job_starter).await?;
 
             // 4. Rosetta (bbopt)
             let rosetta_bin = config
@@ -154,7 +223,7 @@ async fn main() -> anyhow::Result<()> {
                 .as_ref()
                 .ok_or_else(|| anyhow::anyhow!("Rosetta bin path not defined"))?;
             let rosetta = Rosetta::new(rosetta_bin);
-            rosetta.run(&mut poses, "screening_rosetta").await?;
+            rosetta.run(&mut poses, "screening_rosetta", job_starter).await?;
 
             // 5. ESMFold
             let esm_python = config
@@ -162,11 +231,14 @@ async fn main() -> anyhow::Result<()> {
                 .as_ref()
                 .unwrap_or(&config.python_path);
             let esmfold = ESMFold::new(esm_python, aux_scripts);
-            esmfold.run(&mut poses, "screening_esmfold").await?;
+            esmfold.run(&mut poses, "screening_esmfold", job_starter).await?;
 
             // 6. Filtering
             poses.filter_poses_by_value("screening_esmfold_plddt", 70.0, ">=");
             poses.filter_poses_by_rank(10, "screening_rosetta_total_score", true);
+
+            // 7. Audit trail: persist the full backbone→model lineage.
+            poses.save_provenance_graph(poses.work_dir.join("screening_provenance.json"))?;
         }
         Commands::Refinement { cycles, nseq } => {
             info!(
@@ -207,19 +279,19 @@ async fn main() -> anyhow::Result<()> {
 
                 // 1. LigandMPNN
                 info!("Cycle {}: Running LigandMPNN", i);
-                mpnn.run(&mut poses, &format!("cycle_{}_mpnn", i)).await?;
+                mpnn.run(&mut poses, &format!("cycle_{}_mpnn", i), job_starter).await?;
 
                 // 2. ESMFold
                 info!("Cycle {}: Running ESMFold", i);
                 esmfold
-                    .run(&mut poses, &format!("cycle_{}_esmfold", i))
+                    .run(&mut poses, &format!("cycle_{}_esmfold", i), job_starter)
                     .await?;
 
                 // 3. Rosetta
                 info!("Cycle {}: Running Rosetta", i);
                 rosetta.options = "-score:weights ref2015".to_string();
                 rosetta
-                    .run(&mut poses, &format!("cycle_{}_rosetta", i))
+                    .run(&mut poses, &format!("cycle_{}_rosetta", i), job_starter)
                     .await?;
 
                 // Filter? For now just keep all.
@@ -240,7 +312,7 @@ async fn main() -> anyhow::Result<()> {
             if let Some(opts) = options {
                 runner.options = opts;
             }
-            runner.run(&mut poses, "rosetta").await?;
+            runner.run(&mut poses, "rosetta", job_starter).await?;
         }
         Commands::ESMFold { options } => {
             let aux_scripts = config
@@ -255,7 +327,7 @@ async fn main() -> anyhow::Result<()> {
             if let Some(opts) = options {
                 runner.options = opts;
             }
-            runner.run(&mut poses, "esmfold").await?;
+            runner.run(&mut poses, "esmfold", job_starter).await?;
         }
         Commands::ChainRemover { chains } => {
             let aux_scripts = config
@@ -270,7 +342,7 @@ async fn main() -> anyhow::Result<()> {
                 aux_scripts,
             );
             runner.chains = Some(chains);
-            runner.run(&mut poses, "chain_remover").await?;
+            runner.run(&mut poses, "chain_remover", job_starter).await?;
         }
         Commands::ChainAdder {
             copy_chain,
@@ -289,7 +361,56 @@ async fn main() -> anyhow::Result<()> {
                 &copy_chain,
             );
             runner.ref_pdb = Some(ref_pdb);
-            runner.run(&mut poses, "chain_adder").await?;
+            runner.run(&mut poses, "chain_adder", job_starter).await?;
+        }
+        Commands::Run { pipeline } => {
+            info!("Loading pipeline spec: {:?}", pipeline);
+            let spec = crate::pipeline::PipelineSpec::from_file(&pipeline)?;
+            crate::pipeline::run_pipeline(&spec, &config, &mut poses, job_starter).await?;
+        }
+        Commands::Replay { provenance } => {
+            info!("Replaying provenance ledger: {:?}", provenance);
+            crate::provenance::replay(&provenance)?;
+        }
+        Commands::FilterPareto {
+            objectives,
+            keep_fronts,
+            out_json,
+        } => {
+            let parsed: Vec<(String, bool)> = objectives
+                .iter()
+                .map(|spec| match spec.split_once(':') {
+                    Some((col, dir)) => (col.to_string(), dir.eq_ignore_ascii_case("high")),
+                    None => (spec.to_string(), false),
+                })
+                .collect();
+            info!("Pareto filtering on {:?} (keep {} fronts)", parsed, keep_fronts);
+            poses.filter_poses_pareto(&parsed, keep_fronts);
+            let out = out_json.unwrap_or_else(|| work_dir.join("pareto_scores.json"));
+            poses.save_to_json(out)?;
+        }
+        Commands::CompositeScore {
+            out_col,
+            terms,
+            out_json,
+        } => {
+            let parsed: Vec<(String, f64, bool)> = terms
+                .iter()
+                .map(|spec| {
+                    let mut parts = spec.split(':');
+                    let column = parts.next().unwrap_or_default().to_string();
+                    let weight: f64 = parts.next().and_then(|w| w.parse().ok()).unwrap_or(1.0);
+                    let higher_is_better = parts
+                        .next()
+                        .map(|dir| dir.eq_ignore_ascii_case("high"))
+                        .unwrap_or(false);
+                    (column, weight, higher_is_better)
+                })
+                .collect();
+            info!("Composite score '{}' from {:?}", out_col, parsed);
+            poses.add_composite_score(&out_col, &parsed);
+            let out = out_json.unwrap_or_else(|| work_dir.join("composite_scores.json"));
+            poses.save_to_json(out)?;
         }
     }
 
@@ -0,0 +1,284 @@
+use crate::config::Config;
+use crate::jobreport::{JobReport, JobReportBuilder};
+use crate::poses::Poses;
+use crate::runners::{JobStarter, Runner};
+use crate::tools::{
+    esmfold::ESMFold,
+    ligandmpnn::LigandMPNN,
+    protein_edits::{ChainAdder, ChainRemover},
+    rfdiffusion::RFDiffusion,
+    rosetta::Rosetta,
+};
+use anyhow::{Context, Result};
+use log::info;
+use serde::Deserialize;
+use std::path::Path;
+
+/// An ordered list of pipeline steps loaded from a spec file. This turns the
+/// previously hard-coded `Screening`/`Refinement` flows into data so users can
+/// reorder steps, change cutoffs, or add cycles without recompiling.
+#[derive(Debug, Deserialize)]
+pub struct PipelineSpec {
+    pub steps: Vec<Step>,
+}
+
+/// A single step: the tool to run, its per-step options, and optional filter
+/// directives applied to the `Poses` afterwards. `name` is used as the runner
+/// `prefix`.
+#[derive(Debug, Deserialize)]
+pub struct Step {
+    pub name: String,
+    pub tool: String,
+    #[serde(default)]
+    pub options: StepOptions,
+    #[serde(default)]
+    pub filters: Vec<FilterDirective>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StepOptions {
+    pub nseq: Option<usize>,
+    pub num_diffusions: Option<usize>,
+    pub options: Option<String>,
+    pub application: Option<String>,
+    pub copy_chain: Option<String>,
+    pub ref_pdb: Option<String>,
+    pub chains: Option<Vec<String>>,
+}
+
+/// A filter directive mapping onto a `Poses` method.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterDirective {
+    FilterValue {
+        column: String,
+        value: f64,
+        operator: String,
+    },
+    FilterRank {
+        n: usize,
+        column: String,
+        #[serde(default)]
+        ascending: bool,
+    },
+    FilterPareto {
+        objectives: Vec<ParetoObjective>,
+        #[serde(default = "one")]
+        keep_fronts: usize,
+    },
+    CompositeScore {
+        out_col: String,
+        terms: Vec<CompositeTerm>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParetoObjective {
+    pub column: String,
+    #[serde(default)]
+    pub higher_is_better: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompositeTerm {
+    pub column: String,
+    pub weight: f64,
+    #[serde(default)]
+    pub higher_is_better: bool,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl PipelineSpec {
+    /// Load a pipeline spec, picking the parser from the file extension
+    /// (`.toml`, `.yaml`/`.yml`, or JSON otherwise).
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pipeline spec: {:?}", path))?;
+        let spec = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&content).context("Failed to parse pipeline TOML")?,
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&content).context("Failed to parse pipeline YAML")?
+            }
+            _ => serde_json::from_str(&content).context("Failed to parse pipeline JSON")?,
+        };
+        Ok(spec)
+    }
+}
+
+/// Drive a pipeline spec: build each runner from `Config`, run it with the
+/// step name as the prefix, then apply the step's filter directives.
+pub async fn run_pipeline(
+    spec: &PipelineSpec,
+    config: &Config,
+    poses: &mut Poses,
+    job_starter: &dyn JobStarter,
+) -> Result<()> {
+    let work_dir = poses.work_dir.clone();
+    for step in &spec.steps {
+        // Skip stages that already completed in a previous run, restoring their
+        // output poses from the persisted report instead of recomputing.
+        if let Some(report) = JobReport::load_if_complete(&work_dir, &step.name)? {
+            info!(
+                "Resuming: stage '{}' already completed ({} poses), skipping",
+                step.name, report.output_pose_count
+            );
+            poses.df = report.poses;
+            continue;
+        }
+
+        info!("Pipeline step '{}' ({})", step.name, step.tool);
+        let builder = JobReportBuilder::new(&step.name).start(poses.df.len());
+        if let Err(e) = run_step(step, config, poses, job_starter).await {
+            builder.fail(&poses.df).save(&work_dir)?;
+            return Err(e);
+        }
+        apply_filters(&step.filters, poses);
+        builder.complete(&poses.df).save(&work_dir)?;
+    }
+    Ok(())
+}
+
+async fn run_step(
+    step: &Step,
+    config: &Config,
+    poses: &mut Poses,
+    job_starter: &dyn JobStarter,
+) -> Result<()> {
+    let aux_scripts = || {
+        config
+            .protein_edits_scripts_dir
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Auxiliary scripts dir not defined"))
+    };
+
+    match step.tool.as_str() {
+        "rfdiffusion" => {
+            let script = config
+                .rfdiffusion_script
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("RFDiffusion script not defined"))?;
+            let canon = std::fs::canonicalize(script)?;
+            let python = config
+                .rfdiffusion_python
+                .as_ref()
+                .unwrap_or(&config.python_path);
+            let mut runner = RFDiffusion::new(python, canon.to_str().unwrap());
+            if let Some(n) = step.options.num_diffusions {
+                runner.num_diffusions = n;
+            }
+            if let Some(opts) = &step.options.options {
+                runner.options = crate::tools::rfdiffusion::RFDiffusionOptions::parse(opts);
+            }
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        "ligandmpnn" => {
+            let script = config
+                .ligandmpnn_script
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("LigandMPNN script not defined"))?;
+            let canon = std::fs::canonicalize(script)?;
+            let python = config
+                .ligandmpnn_python
+                .as_ref()
+                .unwrap_or(&config.python_path);
+            let mut runner = LigandMPNN::new(python, canon.to_str().unwrap());
+            if let Some(n) = step.options.nseq {
+                runner.nseq = n;
+            }
+            if let Some(opts) = &step.options.options {
+                runner.options = opts.clone();
+            }
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        "rosetta" => {
+            let bin = config
+                .rosetta_bin
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Rosetta bin path not defined"))?;
+            let mut runner = Rosetta::new(bin);
+            if let Some(app) = &step.options.application {
+                runner.application = app.clone();
+            }
+            if let Some(opts) = &step.options.options {
+                runner.options = opts.clone();
+            }
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        "esmfold" => {
+            let aux = aux_scripts()?;
+            let python = config
+                .esmfold_python
+                .as_ref()
+                .unwrap_or(&config.python_path);
+            let mut runner = ESMFold::new(python, aux);
+            if let Some(opts) = &step.options.options {
+                runner.options = opts.clone();
+            }
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        "chain_adder" => {
+            let aux = aux_scripts()?;
+            let python = config
+                .protein_edits_python
+                .as_ref()
+                .unwrap_or(&config.python_path);
+            let copy_chain = step
+                .options
+                .copy_chain
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("chain_adder requires copy_chain"))?;
+            let mut runner = ChainAdder::new(python, aux, copy_chain);
+            runner.ref_pdb = step.options.ref_pdb.clone();
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        "chain_remover" => {
+            let aux = aux_scripts()?;
+            let python = config
+                .protein_edits_python
+                .as_ref()
+                .unwrap_or(&config.python_path);
+            let mut runner = ChainRemover::new(python, aux);
+            runner.chains = step.options.chains.clone();
+            runner.run(poses, &step.name, job_starter).await?;
+        }
+        other => anyhow::bail!("Unknown pipeline tool: {}", other),
+    }
+    Ok(())
+}
+
+fn apply_filters(filters: &[FilterDirective], poses: &mut Poses) {
+    for filter in filters {
+        match filter {
+            FilterDirective::FilterValue {
+                column,
+                value,
+                operator,
+            } => poses.filter_poses_by_value(column, *value, operator),
+            FilterDirective::FilterRank {
+                n,
+                column,
+                ascending,
+            } => poses.filter_poses_by_rank(*n, column, *ascending),
+            FilterDirective::FilterPareto {
+                objectives,
+                keep_fronts,
+            } => {
+                let objs: Vec<(String, bool)> = objectives
+                    .iter()
+                    .map(|o| (o.column.clone(), o.higher_is_better))
+                    .collect();
+                poses.filter_poses_pareto(&objs, *keep_fronts);
+            }
+            FilterDirective::CompositeScore { out_col, terms } => {
+                let terms: Vec<(String, f64, bool)> = terms
+                    .iter()
+                    .map(|t| (t.column.clone(), t.weight, t.higher_is_better))
+                    .collect();
+                poses.add_composite_score(out_col, &terms);
+            }
+        }
+    }
+}
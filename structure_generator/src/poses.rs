@@ -1,21 +1,70 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 
+/// One operation in a pose's lineage: the stage and runner that produced a
+/// record, the exact command that was run, and the parent pose it derived
+/// from. Each `Runner` appends a step as it builds its output records, so the
+/// ordered `Vec<ProvenanceStep>` on a final pose is its full history back to
+/// the first input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceStep {
+    pub stage_prefix: String,
+    pub runner: String,
+    pub command: String,
+    pub input_poses: Option<String>,
+    pub input_description: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoseRecord {
     pub input_poses: Option<String>,
     pub poses: String,
     pub poses_description: String,
 
+    #[serde(default)]
+    pub provenance: Vec<ProvenanceStep>,
+
     #[serde(flatten)]
     pub extra_fields: HashMap<String, Value>,
 }
 
+impl PoseRecord {
+    /// Append a lineage step recording the operation that produced this record.
+    pub fn push_provenance(&mut self, step: ProvenanceStep) {
+        self.provenance.push(step);
+    }
+}
+
+/// A node in the provenance DAG: one pose and the history that produced it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceNode {
+    pub description: String,
+    pub poses: String,
+    pub provenance: Vec<ProvenanceStep>,
+}
+
+/// A parent→child edge between pose descriptions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceEdge {
+    pub parent: String,
+    pub child: String,
+}
+
+/// The full parent→child lineage graph reconstructed from the per-pose
+/// provenance steps.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceGraph {
+    pub nodes: Vec<ProvenanceNode>,
+    pub edges: Vec<ProvenanceEdge>,
+}
+
 #[derive(Debug, Default)]
 pub struct Poses {
     pub work_dir: PathBuf,
@@ -68,6 +117,141 @@ impl Poses {
         });
     }
 
+    /// Combine several score columns into a single weighted, z-normalized
+    /// metric written to `out_col`. Each term is `(column, weight,
+    /// higher_is_better)`. For every term the mean and standard deviation are
+    /// taken over the current `df`, each value is z-normalized as
+    /// `(x - mean) / std` (guarding `std == 0`), the sign is flipped when
+    /// `higher_is_better` is true, then scaled by the weight and summed.
+    /// Records missing a numeric value for any term get a null score so that
+    /// `filter_poses_by_rank` sorts them last. Smaller is better.
+    pub fn add_composite_score(&mut self, out_col: &str, terms: &[(String, f64, bool)]) {
+        // Per-term mean and standard deviation over the records that have a
+        // numeric value for that term.
+        let stats: Vec<(f64, f64)> = terms
+            .iter()
+            .map(|(col, _, _)| {
+                let values: Vec<f64> = self
+                    .df
+                    .iter()
+                    .filter_map(|rec| rec.extra_fields.get(col).and_then(|v| v.as_f64()))
+                    .collect();
+                if values.is_empty() {
+                    return (0.0, 0.0);
+                }
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                let var =
+                    values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64;
+                (mean, var.sqrt())
+            })
+            .collect();
+
+        for rec in &mut self.df {
+            let mut composite = 0.0;
+            let mut missing = false;
+            for ((col, weight, higher_is_better), (mean, std)) in terms.iter().zip(&stats) {
+                match rec.extra_fields.get(col).and_then(|v| v.as_f64()) {
+                    Some(x) => {
+                        let z = if *std == 0.0 { 0.0 } else { (x - mean) / std };
+                        let signed = if *higher_is_better { -z } else { z };
+                        composite += weight * signed;
+                    }
+                    None => {
+                        missing = true;
+                        break;
+                    }
+                }
+            }
+
+            let value = if missing {
+                Value::Null
+            } else {
+                serde_json::Number::from_f64(composite)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            };
+            rec.extra_fields.insert(out_col.to_string(), value);
+        }
+    }
+
+    /// Retain only the records in the first `keep_fronts` Pareto fronts across
+    /// multiple objectives. Each objective is a column name plus a bool for
+    /// "higher is better". Record A dominates B iff A is at least as good as B
+    /// on every objective and strictly better on at least one; missing or
+    /// non-numeric values are treated as the worst possible. The front index is
+    /// recorded into `extra_fields` as `pareto_front`. O(n²·k), fine for the
+    /// ≤ a few thousand poses these runs produce.
+    pub fn filter_poses_pareto(&mut self, objectives: &[(String, bool)], keep_fronts: usize) {
+        let n = self.df.len();
+
+        // Extract each record's objective vector, normalised so that *larger is
+        // always better* (flip the sign for minimisation objectives), with the
+        // worst case (NaN treated as -inf) for missing/non-numeric values.
+        let values: Vec<Vec<f64>> = self
+            .df
+            .iter()
+            .map(|rec| {
+                objectives
+                    .iter()
+                    .map(|(col, higher_is_better)| {
+                        rec.extra_fields
+                            .get(col)
+                            .and_then(|v| v.as_f64())
+                            .map(|v| if *higher_is_better { v } else { -v })
+                            .unwrap_or(f64::NEG_INFINITY)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // `a` dominates `b` in maximisation space.
+        let dominates = |a: &[f64], b: &[f64]| -> bool {
+            let mut strictly_better = false;
+            for (x, y) in a.iter().zip(b.iter()) {
+                if x < y {
+                    return false;
+                }
+                if x > y {
+                    strictly_better = true;
+                }
+            }
+            strictly_better
+        };
+
+        // Assign a front index to every record by repeated non-dominated sorting.
+        let mut front_of = vec![usize::MAX; n];
+        let mut remaining: Vec<usize> = (0..n).collect();
+        let mut front = 0;
+        while !remaining.is_empty() {
+            let current: Vec<usize> = remaining
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    !remaining
+                        .iter()
+                        .any(|&j| j != i && dominates(&values[j], &values[i]))
+                })
+                .collect();
+            for &i in &current {
+                front_of[i] = front;
+            }
+            remaining.retain(|i| !current.contains(i));
+            front += 1;
+        }
+
+        // Record the front index and drop everything past `keep_fronts`.
+        let mut keep = Vec::new();
+        for (i, rec) in self.df.drain(..).enumerate() {
+            let mut rec = rec;
+            rec.extra_fields
+                .insert("pareto_front".to_string(), Value::from(front_of[i] as u64));
+            if front_of[i] < keep_fronts {
+                keep.push(rec);
+            }
+        }
+        self.df = keep;
+    }
+
     pub fn filter_poses_by_rank(&mut self, n: usize, score_col: &str, ascending: bool) {
         self.df.sort_by(|a, b| {
             let va = a
@@ -88,4 +272,57 @@ impl Poses {
         });
         self.df.truncate(n);
     }
+
+    /// Reconstruct the parent→child lineage DAG from every record's ordered
+    /// provenance steps. Each step's `input_description` is the parent of the
+    /// description it produced; the chain ends at the record's current
+    /// description. Duplicate nodes and edges are collapsed, so a fan-out
+    /// (e.g. one RFDiffusion backbone yielding many diffusion indices) appears
+    /// as a single parent with many children.
+    pub fn provenance_graph(&self) -> ProvenanceGraph {
+        let mut nodes: Vec<ProvenanceNode> = Vec::new();
+        let mut seen_nodes: HashSet<String> = HashSet::new();
+        let mut edges: Vec<ProvenanceEdge> = Vec::new();
+        let mut seen_edges: HashSet<(String, String)> = HashSet::new();
+
+        for rec in &self.df {
+            let mut chain: Vec<String> = Vec::new();
+            for step in &rec.provenance {
+                if let Some(desc) = &step.input_description {
+                    chain.push(desc.clone());
+                }
+            }
+            chain.push(rec.poses_description.clone());
+
+            for pair in chain.windows(2) {
+                let edge = (pair[0].clone(), pair[1].clone());
+                if edge.0 != edge.1 && seen_edges.insert(edge.clone()) {
+                    edges.push(ProvenanceEdge {
+                        parent: edge.0,
+                        child: edge.1,
+                    });
+                }
+            }
+
+            if seen_nodes.insert(rec.poses_description.clone()) {
+                nodes.push(ProvenanceNode {
+                    description: rec.poses_description.clone(),
+                    poses: rec.poses.clone(),
+                    provenance: rec.provenance.clone(),
+                });
+            }
+        }
+
+        ProvenanceGraph { nodes, edges }
+    }
+
+    /// Serialize the provenance DAG to `path` as pretty JSON.
+    pub fn save_provenance_graph(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path.as_ref()).context(format!(
+            "Failed to create provenance graph file: {:?}",
+            path.as_ref()
+        ))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.provenance_graph())?;
+        Ok(())
+    }
 }
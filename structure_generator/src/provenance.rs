@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A file referenced by a recorded command, tagged with its content hash so a
+/// replay can detect drift.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// A single external command executed by a runner, captured so that a run can
+/// be audited and deterministically replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceEntry {
+    pub step_prefix: String,
+    pub command: String,
+    pub cwd: String,
+    pub input_files: Vec<FileHash>,
+    pub output_files: Vec<FileHash>,
+    pub exit_code: Option<i32>,
+    pub wall_time_secs: f64,
+    pub tool_version: String,
+}
+
+/// Append-only JSONL ledger written next to the poses JSON at `path`.
+#[derive(Debug)]
+pub struct Ledger {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl Ledger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Append one entry as a JSON line. Serialised under a lock so concurrent
+    /// runners don't interleave partial writes.
+    pub fn append(&self, entry: &ProvenanceEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open provenance ledger: {:?}", self.path))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Hash a file's contents, returning a lowercase hex digest.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash every whitespace-delimited token of `command` that names an existing
+/// file, relative to `cwd`. A cheap way to capture a command's inputs without
+/// each runner having to declare them.
+pub fn hash_command_files(command: &str, cwd: &Path) -> Vec<FileHash> {
+    let mut hashes = Vec::new();
+    for token in command.split_whitespace() {
+        let candidate = Path::new(token);
+        let resolved = if candidate.is_absolute() {
+            candidate.to_path_buf()
+        } else {
+            cwd.join(candidate)
+        };
+        if resolved.is_file() {
+            if let Ok(sha256) = hash_file(&resolved) {
+                hashes.push(FileHash {
+                    path: resolved.to_string_lossy().to_string(),
+                    sha256,
+                });
+            }
+        }
+    }
+    hashes
+}
+
+/// Re-execute the commands recorded in `ledger_path` in order and verify that
+/// each recorded output file still hashes to the same value. Fails loudly on
+/// the first drift so the audit trail stays trustworthy.
+pub fn replay(ledger_path: &Path) -> Result<()> {
+    let file = File::open(ledger_path)
+        .with_context(|| format!("Failed to open provenance ledger: {:?}", ledger_path))?;
+    let reader = BufReader::new(file);
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: ProvenanceEntry = serde_json::from_str(&line)
+            .with_context(|| format!("Malformed provenance entry on line {}", i + 1))?;
+
+        info!(
+            "Replaying [{}] {} (cwd: {})",
+            entry.step_prefix, entry.command, entry.cwd
+        );
+
+        let replayed = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&entry.command)
+            .current_dir(&entry.cwd)
+            .output()
+            .with_context(|| format!("Failed to replay command: {}", entry.command))?;
+
+        if replayed.status.code() != entry.exit_code {
+            error!(
+                "Exit code drift on line {}: recorded {:?}, got {:?}",
+                i + 1,
+                entry.exit_code,
+                replayed.status.code()
+            );
+            anyhow::bail!("Replay diverged from recorded exit code");
+        }
+
+        // The original run teed stdout/stderr into `task_*.out`/`.err` log
+        // files, which are recorded like any other output artifact; replay
+        // doesn't tee, so rewrite those same paths here before hashing, or
+        // they'd trivially "match" the untouched originals forever.
+        for output in &entry.output_files {
+            if output.path.ends_with(".out") {
+                std::fs::write(&output.path, &replayed.stdout)
+                    .with_context(|| format!("Failed to rewrite stdout log: {}", output.path))?;
+            } else if output.path.ends_with(".err") {
+                std::fs::write(&output.path, &replayed.stderr)
+                    .with_context(|| format!("Failed to rewrite stderr log: {}", output.path))?;
+            }
+        }
+
+        for output in &entry.output_files {
+            let actual = hash_file(Path::new(&output.path)).with_context(|| {
+                format!("Recorded output file missing during replay: {}", output.path)
+            })?;
+            if actual != output.sha256 {
+                error!(
+                    "Output hash drift for {}: recorded {}, got {}",
+                    output.path, output.sha256, actual
+                );
+                anyhow::bail!("Replay produced different output than recorded");
+            }
+        }
+    }
+
+    info!("Replay completed with no drift");
+    Ok(())
+}
@@ -1,19 +1,282 @@
-use crate::poses::{PoseRecord, Poses};
+use crate::poses::Poses;
+use crate::provenance::{hash_command_files, hash_file, FileHash, Ledger, ProvenanceEntry};
 use anyhow::{Context, Result};
-use log::{error, info};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use log::{error, info, warn};
+use std::os::unix::io::RawFd;
+use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::process::Command;
+use tokio::sync::Semaphore;
 
-use async_trait::async_trait;
+/// GNU make jobserver: an OS pipe preloaded with `tokens` bytes. Sub-tools that
+/// honour the protocol grab a token before starting extra work and return it
+/// afterwards, so they share this process's global parallelism budget rather
+/// than oversubscribing the machine.
+struct JobServer {
+    read_fd: RawFd,
+    write_fd: RawFd,
+}
+
+impl JobServer {
+    fn new(tokens: usize) -> std::io::Result<Self> {
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let (read_fd, write_fd) = (fds[0], fds[1]);
+        // Preload the pipe with one token per additional slot (N-1).
+        let buf = vec![b'+'; tokens];
+        unsafe {
+            libc::write(write_fd, buf.as_ptr() as *const libc::c_void, buf.len());
+        }
+        Ok(Self { read_fd, write_fd })
+    }
+
+    fn makeflags(&self) -> String {
+        format!("--jobserver-auth={},{}", self.read_fd, self.write_fd)
+    }
+}
+
+impl Drop for JobServer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
+
+/// Bounds the number of concurrent local child processes via a token pool, and
+/// exposes a GNU make jobserver so cooperating sub-tools share the same limit.
+pub struct JobPool {
+    pub max_parallel: usize,
+    semaphore: Arc<Semaphore>,
+    jobserver: Option<JobServer>,
+}
+
+impl JobPool {
+    pub fn new(max_parallel: usize) -> Self {
+        let n = max_parallel.max(1);
+        let jobserver = JobServer::new(n.saturating_sub(1)).ok();
+        Self {
+            max_parallel: n,
+            semaphore: Arc::new(Semaphore::new(n)),
+            jobserver,
+        }
+    }
+
+    /// `MAKEFLAGS` value to export into children, if a jobserver was created.
+    fn makeflags(&self) -> Option<String> {
+        self.jobserver.as_ref().map(|j| j.makeflags())
+    }
+}
+
+impl Default for JobPool {
+    fn default() -> Self {
+        let n = std::thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1);
+        Self::new(n)
+    }
+}
 
 #[async_trait]
 pub trait Runner {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()>;
+    async fn run(&self, poses: &mut Poses, prefix: &str, job_starter: &dyn JobStarter)
+        -> Result<()>;
+}
+
+/// Options describing how a batch of commands should be dispatched. The local
+/// backend reads only `parallelism`; the cluster fields are consumed by
+/// [`SlurmJobStarter`] when building the sbatch script.
+#[derive(Debug, Clone)]
+pub struct JobOptions {
+    /// Partition / queue to submit to (SLURM `--partition`).
+    pub partition: Option<String>,
+    /// CPU cores requested per array task (`--cpus-per-task`).
+    pub cores: usize,
+    /// Memory requested per array task, e.g. `"8G"` (`--mem`).
+    pub memory: Option<String>,
+    /// Upper bound on concurrently running array tasks (`--array=1-N%max`).
+    pub max_array_tasks: Option<usize>,
+    /// Per-stage cap on concurrent local processes; `None` uses the pool's
+    /// global limit. Lets callers pin RFDiffusion to one GPU job while letting
+    /// cheaper stages run wide.
+    pub parallelism: Option<usize>,
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self {
+            partition: None,
+            cores: 1,
+            memory: None,
+            max_array_tasks: None,
+            parallelism: None,
+        }
+    }
+}
+
+/// Result of a single submitted command once it has finished.
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    /// Position of the command within the submitted batch.
+    pub index: usize,
+    /// Process exit code, or `None` if the task was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// Path the task's stdout was written to, if any.
+    pub stdout_path: Option<PathBuf>,
+    /// Path the task's stderr was written to, if any.
+    pub stderr_path: Option<PathBuf>,
+}
+
+impl JobHandle {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Per-command result collected once a whole batch has finished. Separates the
+/// exit state of each command from the mechanics of submission and scheduling.
+#[derive(Debug, Clone)]
+pub struct JobOutcome {
+    pub index: usize,
+    pub exit_code: Option<i32>,
+    pub stdout_path: Option<PathBuf>,
+    pub stderr_path: Option<PathBuf>,
 }
 
-pub struct LocalJobStarter;
+impl JobOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Backend responsible for executing a set of shell commands. Runners hand
+/// their per-pose tool commands to a `&dyn JobStarter` instead of spawning them
+/// directly, so the same pipeline can run locally or fan the many per-pose
+/// commands out onto a cluster.
+#[async_trait]
+pub trait JobStarter: Send + Sync {
+    async fn submit(
+        &self,
+        cmds: &[String],
+        work_dir: &Path,
+        opts: &JobOptions,
+    ) -> Result<Vec<JobHandle>>;
+
+    async fn wait_all(&self, handles: &mut [JobHandle]) -> Result<()>;
+
+    /// Submit a whole batch, block until every command finishes, and map the
+    /// results back to per-command [`JobOutcome`]s. This is the entry point
+    /// runners use so they never deal with submission or scheduling directly;
+    /// `submit`/`wait_all` stay on the trait for backends (or tests) that need
+    /// to inspect in-flight handles between the two steps.
+    async fn submit_batch(
+        &self,
+        cmds: &[String],
+        work_dir: &Path,
+        opts: &JobOptions,
+    ) -> Result<Vec<JobOutcome>> {
+        let mut handles = self.submit(cmds, work_dir, opts).await?;
+        self.wait_all(&mut handles).await?;
+        Ok(handles
+            .into_iter()
+            .map(|h| JobOutcome {
+                index: h.index,
+                exit_code: h.exit_code,
+                stdout_path: h.stdout_path,
+                stderr_path: h.stderr_path,
+            })
+            .collect())
+    }
+}
+
+/// A child process that did not exit cleanly, distinguishing a non-zero exit
+/// code from termination by a signal. Both variants carry the full command,
+/// working directory, and the tail of captured stderr for actionable
+/// diagnostics.
+#[derive(Debug)]
+pub enum CommandError {
+    ExitCode {
+        command: String,
+        cwd: String,
+        code: i32,
+        stderr_tail: String,
+    },
+    Signal {
+        command: String,
+        cwd: String,
+        signal: i32,
+        stderr_tail: String,
+    },
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::ExitCode {
+                command,
+                cwd,
+                code,
+                stderr_tail,
+            } => write!(
+                f,
+                "command `{}` (cwd: {}) exited with code {}\n--- stderr tail ---\n{}",
+                command, cwd, code, stderr_tail
+            ),
+            CommandError::Signal {
+                command,
+                cwd,
+                signal,
+                stderr_tail,
+            } => write!(
+                f,
+                "command `{}` (cwd: {}) terminated by signal {}\n--- stderr tail ---\n{}",
+                command, cwd, signal, stderr_tail
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Last `n` lines of `bytes`, lossily decoded — used to surface just the
+/// relevant end of a failed command's stderr.
+fn stderr_tail(bytes: &[u8], n: usize) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(n);
+    lines[start..].join("\n")
+}
+
+#[derive(Default)]
+pub struct LocalJobStarter {
+    /// Optional provenance ledger; when set, every submitted command is
+    /// recorded for auditing and replay.
+    pub ledger: Option<Arc<Ledger>>,
+    /// Token pool bounding concurrent child processes.
+    pub pool: JobPool,
+}
 
 impl LocalJobStarter {
+    pub fn new(ledger: Option<Arc<Ledger>>) -> Self {
+        Self {
+            ledger,
+            pool: JobPool::default(),
+        }
+    }
+
+    pub fn with_pool(ledger: Option<Arc<Ledger>>, pool: JobPool) -> Self {
+        Self { ledger, pool }
+    }
+
+    /// Run a single command to completion in `working_dir`, surfacing stderr on
+    /// failure. Kept for callers that only ever run one command at a time.
     pub async fn run_command(cmd_str: &str, working_dir: &Path) -> Result<()> {
         info!("Running command: {}", cmd_str);
 
@@ -42,3 +305,412 @@ impl LocalJobStarter {
         Ok(())
     }
 }
+
+impl LocalJobStarter {
+    /// Run a single command under a pool permit, tee its output to log files,
+    /// and record provenance. Returns the resulting handle.
+    async fn run_one(
+        &self,
+        index: usize,
+        cmd: &str,
+        work_dir: &Path,
+        log_dir: &Path,
+        step_prefix: &str,
+    ) -> Result<JobHandle> {
+        // Hold a pool permit for the lifetime of the child process so no more
+        // than `max_parallel` commands run at once.
+        let _permit = self.pool.semaphore.acquire().await.unwrap();
+
+        info!("Running command [{}]: {}", index, cmd);
+
+        let stdout_path = log_dir.join(format!("task_{:04}.out", index));
+        let stderr_path = log_dir.join(format!("task_{:04}.err", index));
+
+        // Hash referenced inputs before the command mutates the tree.
+        let input_files = self
+            .ledger
+            .as_ref()
+            .map(|_| hash_command_files(cmd, work_dir))
+            .unwrap_or_default();
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(cmd).current_dir(work_dir);
+        // Let cooperating sub-tools share our parallelism budget.
+        if let Some(makeflags) = self.pool.makeflags() {
+            command.env("MAKEFLAGS", makeflags);
+        }
+
+        let started = Instant::now();
+        let output = command
+            .output()
+            .await
+            .with_context(|| format!("Failed to execute command: {}", cmd))?;
+        let wall_time_secs = started.elapsed().as_secs_f64();
+
+        // Tee both streams to per-command log files regardless of outcome.
+        tokio::fs::write(&stdout_path, &output.stdout).await?;
+        tokio::fs::write(&stderr_path, &output.stderr).await?;
+
+        if let Some(ledger) = &self.ledger {
+            // Log files plus whatever real artifacts (PDBs, score JSONs, ...)
+            // the command left behind. `hash_command_files` finds them the
+            // same way it finds inputs: any whitespace token that resolves to
+            // an existing file, just checked after the command ran instead of
+            // before, so `replay` has something more meaningful than the logs
+            // to detect drift against.
+            let mut output_files: Vec<FileHash> = [&stdout_path, &stderr_path]
+                .iter()
+                .filter_map(|p| {
+                    hash_file(p).ok().map(|sha256| FileHash {
+                        path: p.to_string_lossy().to_string(),
+                        sha256,
+                    })
+                })
+                .collect();
+            output_files.extend(hash_command_files(cmd, work_dir));
+            let tool_version = shell_words::split(cmd)
+                .ok()
+                .and_then(|args| args.into_iter().next())
+                .and_then(|bin| {
+                    Path::new(&bin)
+                        .file_name()
+                        .map(|s| s.to_string_lossy().to_string())
+                })
+                .unwrap_or_default();
+            let entry = ProvenanceEntry {
+                step_prefix: step_prefix.to_string(),
+                command: cmd.to_string(),
+                cwd: work_dir.to_string_lossy().to_string(),
+                input_files,
+                output_files,
+                exit_code: output.status.code(),
+                wall_time_secs,
+                tool_version,
+            };
+            if let Err(e) = ledger.append(&entry) {
+                warn!("Failed to record provenance entry: {}", e);
+            }
+        }
+
+        // Surface a precise failure: a non-zero exit code and a signal-induced
+        // termination are distinct conditions that callers reason about
+        // differently. The full command, cwd, and stderr tail travel with the
+        // error so logs need not be consulted for the common case.
+        if !output.status.success() {
+            let tail = stderr_tail(&output.stderr, 20);
+            let err = if let Some(signal) = output.status.signal() {
+                CommandError::Signal {
+                    command: cmd.to_string(),
+                    cwd: work_dir.to_string_lossy().to_string(),
+                    signal,
+                    stderr_tail: tail,
+                }
+            } else {
+                CommandError::ExitCode {
+                    command: cmd.to_string(),
+                    cwd: work_dir.to_string_lossy().to_string(),
+                    code: output.status.code().unwrap_or(-1),
+                    stderr_tail: tail,
+                }
+            };
+            warn!("Command [{}] failed: {}", index, err);
+            return Err(err.into());
+        }
+
+        Ok(JobHandle {
+            index,
+            exit_code: output.status.code(),
+            stdout_path: Some(stdout_path),
+            stderr_path: Some(stderr_path),
+        })
+    }
+}
+
+#[async_trait]
+impl JobStarter for LocalJobStarter {
+    async fn submit(
+        &self,
+        cmds: &[String],
+        work_dir: &Path,
+        opts: &JobOptions,
+    ) -> Result<Vec<JobHandle>> {
+        let log_dir = work_dir.join("logs");
+        tokio::fs::create_dir_all(&log_dir)
+            .await
+            .context("Failed to create log directory")?;
+
+        let step_prefix = work_dir
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // Independent poses run in parallel, bounded by the per-stage override
+        // or the pool's global limit, whichever is smaller.
+        let concurrency = opts
+            .parallelism
+            .unwrap_or(self.pool.max_parallel)
+            .clamp(1, self.pool.max_parallel);
+
+        let mut handles: Vec<JobHandle> = stream::iter(cmds.iter().enumerate())
+            .map(|(index, cmd)| self.run_one(index, cmd, work_dir, &log_dir, &step_prefix))
+            .buffer_unordered(concurrency)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        // buffer_unordered yields out of order; restore submission order.
+        handles.sort_by_key(|h| h.index);
+        Ok(handles)
+    }
+
+    async fn wait_all(&self, handles: &mut [JobHandle]) -> Result<()> {
+        // Local jobs are already finished by the time `submit` returns; just
+        // verify none of them failed.
+        for handle in handles.iter() {
+            if !handle.succeeded() {
+                return Err(anyhow::anyhow!(
+                    "Local command {} failed with exit code {:?}",
+                    handle.index,
+                    handle.exit_code
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Submits a batch of commands to SLURM as a single array job. Each command is
+/// written into a lookup file dispatched on `$SLURM_ARRAY_TASK_ID`, then
+/// `squeue`/`sacct` are polled until every array task has finished.
+pub struct SlurmJobStarter {
+    /// How often to poll the scheduler for completion.
+    pub poll_interval: Duration,
+    /// Cluster resource defaults, used whenever a per-submit option is unset.
+    pub opts: JobOptions,
+    /// Scheduler job id of the most recent submission, used by `wait_all`.
+    job_id: Mutex<Option<String>>,
+}
+
+impl SlurmJobStarter {
+    pub fn new(opts: JobOptions) -> Self {
+        Self {
+            poll_interval: Duration::from_secs(15),
+            opts,
+            job_id: Mutex::new(None),
+        }
+    }
+
+    /// Resolve the options for a submission: explicit per-call values win,
+    /// falling back to the backend's configured defaults.
+    fn effective_opts(&self, opts: &JobOptions) -> JobOptions {
+        JobOptions {
+            partition: opts.partition.clone().or_else(|| self.opts.partition.clone()),
+            cores: if opts.cores != 1 { opts.cores } else { self.opts.cores },
+            memory: opts.memory.clone().or_else(|| self.opts.memory.clone()),
+            max_array_tasks: opts.max_array_tasks.or(self.opts.max_array_tasks),
+            parallelism: opts.parallelism.or(self.opts.parallelism),
+        }
+    }
+}
+
+impl Default for SlurmJobStarter {
+    fn default() -> Self {
+        Self::new(JobOptions::default())
+    }
+}
+
+#[async_trait]
+impl JobStarter for SlurmJobStarter {
+    async fn submit(
+        &self,
+        cmds: &[String],
+        work_dir: &Path,
+        opts: &JobOptions,
+    ) -> Result<Vec<JobHandle>> {
+        let opts = self.effective_opts(opts);
+        let log_dir = work_dir.join("logs");
+        tokio::fs::create_dir_all(&log_dir)
+            .await
+            .context("Failed to create log directory")?;
+
+        // Write one command per line; the batch script selects its line from
+        // `$SLURM_ARRAY_TASK_ID` (1-based), so array task N runs command N-1.
+        let cmds_file = work_dir.join("array_cmds.txt");
+        tokio::fs::write(&cmds_file, cmds.join("\n"))
+            .await
+            .context("Failed to write array command file")?;
+
+        let array_spec = match opts.max_array_tasks {
+            Some(max) => format!("1-{}%{}", cmds.len(), max),
+            None => format!("1-{}", cmds.len()),
+        };
+
+        let mut script = String::from("#!/bin/bash\n");
+        if let Some(partition) = &opts.partition {
+            script.push_str(&format!("#SBATCH --partition={}\n", partition));
+        }
+        script.push_str(&format!("#SBATCH --cpus-per-task={}\n", opts.cores));
+        if let Some(mem) = &opts.memory {
+            script.push_str(&format!("#SBATCH --mem={}\n", mem));
+        }
+        script.push_str(&format!("#SBATCH --array={}\n", array_spec));
+        script.push_str(&format!(
+            "#SBATCH --output={}/task_%a.out\n",
+            log_dir.to_string_lossy()
+        ));
+        script.push_str(&format!(
+            "#SBATCH --error={}/task_%a.err\n",
+            log_dir.to_string_lossy()
+        ));
+        script.push_str(&format!(
+            "CMD=$(sed -n \"${{SLURM_ARRAY_TASK_ID}}p\" {})\n",
+            cmds_file.to_string_lossy()
+        ));
+        script.push_str("eval \"$CMD\"\n");
+
+        let script_path = work_dir.join("submit.sbatch");
+        tokio::fs::write(&script_path, script)
+            .await
+            .context("Failed to write sbatch script")?;
+
+        let output = Command::new("sbatch")
+            .arg("--parsable")
+            .arg(&script_path)
+            .current_dir(work_dir)
+            .output()
+            .await
+            .context("Failed to invoke sbatch")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("sbatch submission failed: {}", stderr));
+        }
+
+        let job_id = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        info!("Submitted SLURM array job {} ({} tasks)", job_id, cmds.len());
+        *self.job_id.lock().unwrap() = Some(job_id);
+
+        Ok(cmds
+            .iter()
+            .enumerate()
+            .map(|(index, _)| JobHandle {
+                index,
+                exit_code: None,
+                stdout_path: Some(log_dir.join(format!("task_{}.out", index + 1))),
+                stderr_path: Some(log_dir.join(format!("task_{}.err", index + 1))),
+            })
+            .collect())
+    }
+
+    async fn wait_all(&self, handles: &mut [JobHandle]) -> Result<()> {
+        let job_id = self
+            .job_id
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("wait_all called before a SLURM job was submitted"))?;
+
+        // Poll `squeue` for the array until no tasks remain.
+        loop {
+            let squeue = Command::new("squeue")
+                .arg("--job")
+                .arg(&job_id)
+                .arg("--noheader")
+                .output()
+                .await
+                .context("Failed to invoke squeue")?;
+            if String::from_utf8_lossy(&squeue.stdout).trim().is_empty() {
+                break;
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        // Collect per-task exit codes from `sacct`. Each array task reports
+        // under `<job_id>_<task>`, plus `.batch`/`.extern` sub-steps we are not
+        // interested in; `ExitCode` is `"<code>:<signal>"`, matching the same
+        // code-vs-signal distinction `JobHandle::exit_code` already encodes.
+        let sacct = Command::new("sacct")
+            .arg("--jobs")
+            .arg(&job_id)
+            .arg("--noheader")
+            .arg("--parsable2")
+            .arg("--format=JobID,State,ExitCode")
+            .output()
+            .await
+            .context("Failed to invoke sacct")?;
+        let report = String::from_utf8_lossy(&sacct.stdout);
+
+        let mut failures: Vec<String> = Vec::new();
+        for line in report.lines() {
+            let mut fields = line.split('|');
+            let (Some(task_job_id), Some(state), Some(exit_code_field)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Some(task_num) = array_task_number(task_job_id, &job_id) else {
+                continue;
+            };
+            let Some(idx) = task_num.checked_sub(1) else {
+                continue;
+            };
+            let Some(handle) = handles.get_mut(idx) else {
+                continue;
+            };
+
+            handle.exit_code = parse_sacct_exit_code(exit_code_field);
+            if !handle.succeeded() {
+                failures.push(format!(
+                    "task {} ({}, exit code {:?}): {}",
+                    idx, task_job_id, handle.exit_code, state
+                ));
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(anyhow::anyhow!(
+                "SLURM array {} had {} failed task(s):\n{}",
+                job_id,
+                failures.len(),
+                failures.join("\n")
+            ));
+        }
+
+        info!("All {} SLURM array tasks completed", handles.len());
+        Ok(())
+    }
+}
+
+/// Parses a `sacct` `JobID` field (e.g. `"12345_3"`) into its 1-based array
+/// task number, provided it is the top-level step for `parent_job_id` and not
+/// a `.batch`/`.extern` sub-step.
+fn array_task_number(job_id_field: &str, parent_job_id: &str) -> Option<usize> {
+    if job_id_field.contains('.') {
+        return None;
+    }
+    job_id_field
+        .strip_prefix(parent_job_id)?
+        .strip_prefix('_')?
+        .parse()
+        .ok()
+}
+
+/// Parses a `sacct` `ExitCode` field (`"<code>:<signal>"`) into the same
+/// `Option<i32>` shape as [`JobHandle::exit_code`]: `None` when the task was
+/// killed by a signal, `Some(code)` otherwise.
+fn parse_sacct_exit_code(exit_code_field: &str) -> Option<i32> {
+    let mut parts = exit_code_field.splitn(2, ':');
+    let code: i32 = parts.next()?.parse().ok()?;
+    let signal: i32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    if signal != 0 {
+        None
+    } else {
+        Some(code)
+    }
+}
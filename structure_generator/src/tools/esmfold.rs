@@ -1,13 +1,14 @@
-use crate::poses::{PoseRecord, Poses};
-use crate::runners::Runner;
+use crate::poses::{PoseRecord, Poses, ProvenanceStep};
+use crate::runners::{JobOptions, JobStarter, Runner};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::Utc;
 use log::info;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use tokio::fs;
 
 pub struct ESMFold {
@@ -43,8 +44,18 @@ impl ESMFold {
             let mut content = String::new();
             File::open(path)?.read_to_string(&mut content)?;
 
-            // Append with newline
-            writeln!(batch_file, "{}", content.trim())?;
+            // Encode the pose description into the FASTA header so the origin
+            // can be recovered from the prediction output (whose files are
+            // named after this header). The body is the sequence lines of the
+            // input, with any pre-existing header dropped to avoid duplicates.
+            writeln!(batch_file, ">{}", pose.poses_description)?;
+            for line in content.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('>') {
+                    continue;
+                }
+                writeln!(batch_file, "{}", line)?;
+            }
         }
 
         Ok(vec![batch_path])
@@ -53,7 +64,12 @@ impl ESMFold {
 
 #[async_trait]
 impl Runner for ESMFold {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         fs::create_dir_all(&work_dir).await?;
         let work_dir_canon = std::fs::canonicalize(&work_dir)?;
@@ -67,26 +83,31 @@ impl Runner for ESMFold {
         let preds_dir = work_dir_canon.join("esm_preds");
         fs::create_dir_all(&preds_dir).await?;
 
-        // Run inference for each batch
-        for fasta_file in fasta_files {
-            // cmd: python esmfold_inference.py --fasta input.fa --output_dir output_dir {options}
-            let cmd = format!(
-                "{} {} --fasta {:?} --output_dir {:?} {}",
-                self.python_path, self.script_path, fasta_file, preds_dir, self.options
-            );
-
-            info!("Executing: {}", cmd);
-            let mut child = Command::new("sh")
-                .arg("-c")
-                .arg(&cmd)
-                .current_dir(&work_dir_canon)
-                .spawn()?;
-
-            let status = child.wait()?;
-            if !status.success() {
-                anyhow::bail!("ESMFold failed for fasta {:?}", fasta_file);
-            }
-        }
+        // Build one inference command per batch and dispatch them together.
+        let cmds: Vec<String> = fasta_files
+            .iter()
+            .map(|fasta_file| {
+                // cmd: python esmfold_inference.py --fasta input.fa --output_dir output_dir {options}
+                format!(
+                    "{} {} --fasta {:?} --output_dir {:?} {}",
+                    self.python_path, self.script_path, fasta_file, preds_dir, self.options
+                )
+            })
+            .collect();
+
+        job_starter
+            .submit_batch(&cmds, &work_dir_canon, &JobOptions::default())
+            .await?;
+
+        // Map each FASTA header (== pose description) back to the originating
+        // record so the ESMFold output can restore `input_poses` and extend the
+        // lineage instead of dropping the origin.
+        let cmd = cmds.first().cloned().unwrap_or_default();
+        let origins: HashMap<String, PoseRecord> = poses
+            .df
+            .iter()
+            .map(|p| (p.poses_description.clone(), p.clone()))
+            .collect();
 
         // Collect scores
         // ProtFlow: esm_preds contains .json and .pdb files (maybe in subdirs?)
@@ -107,12 +128,26 @@ impl Runner for ESMFold {
                 // Check subdir
                 let mut sub_read = fs::read_dir(&path).await?;
                 while let Some(sub_entry) = sub_read.next_entry().await? {
-                    self.process_file(&sub_entry.path(), &output_pdbs_dir, &mut new_records)
-                        .await?;
+                    self.process_file(
+                        &sub_entry.path(),
+                        &output_pdbs_dir,
+                        prefix,
+                        &cmd,
+                        &origins,
+                        &mut new_records,
+                    )
+                    .await?;
                 }
             } else {
-                self.process_file(&path, &output_pdbs_dir, &mut new_records)
-                    .await?;
+                self.process_file(
+                    &path,
+                    &output_pdbs_dir,
+                    prefix,
+                    &cmd,
+                    &origins,
+                    &mut new_records,
+                )
+                .await?;
             }
         }
 
@@ -123,10 +158,14 @@ impl Runner for ESMFold {
 }
 
 impl ESMFold {
+    #[allow(clippy::too_many_arguments)]
     async fn process_file(
         &self,
         path: &Path,
         output_pdbs_dir: &Path,
+        prefix: &str,
+        cmd: &str,
+        origins: &HashMap<String, PoseRecord>,
         records: &mut Vec<PoseRecord>,
     ) -> Result<()> {
         if path.extension().map_or(false, |ext| ext == "json") {
@@ -144,18 +183,29 @@ impl ESMFold {
                 fs::copy(&pdb_path, &new_pdb_path).await?;
 
                 let content = fs::read_to_string(path).await?;
-                let data: std::collections::HashMap<String, Value> =
-                    serde_json::from_str(&content)?;
+                let data: HashMap<String, Value> = serde_json::from_str(&content)?;
 
-                // Extract description from filename or json?
+                // The prediction file name is the FASTA header we wrote in
+                // `prep_fastas`, i.e. the input pose's description. Use it to
+                // recover the origin and carry the lineage forward.
                 let desc = stem.to_string_lossy().to_string();
+                let origin = origins.get(&desc);
 
-                let record = PoseRecord {
-                    input_poses: None, // Lost origin for now unless we track it in fasta headers and map back
+                let mut record = PoseRecord {
+                    input_poses: origin.map(|o| o.poses.clone()),
                     poses: new_pdb_path.to_string_lossy().to_string(),
-                    poses_description: desc,
+                    poses_description: desc.clone(),
+                    provenance: origin.map(|o| o.provenance.clone()).unwrap_or_default(),
                     extra_fields: data,
                 };
+                record.push_provenance(ProvenanceStep {
+                    stage_prefix: prefix.to_string(),
+                    runner: "ESMFold".to_string(),
+                    command: cmd.to_string(),
+                    input_poses: origin.map(|o| o.poses.clone()),
+                    input_description: Some(desc),
+                    timestamp: Utc::now(),
+                });
                 records.push(record);
             }
         }
@@ -1,7 +1,8 @@
-use crate::poses::{PoseRecord, Poses};
-use crate::runners::{LocalJobStarter, Runner};
+use crate::poses::{PoseRecord, Poses, ProvenanceStep};
+use crate::runners::{JobOptions, JobStarter, Runner};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::Utc;
 use log::warn;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -65,22 +66,31 @@ impl LigandMPNN {
 
 #[async_trait]
 impl Runner for LigandMPNN {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         let seq_dir = work_dir.join("seqs");
         tokio::fs::create_dir_all(&seq_dir)
             .await
             .context("Failed to create working directories")?;
 
+        // One command per pose, dispatched as a single batch.
+        let cmds = poses
+            .df
+            .iter()
+            .map(|pose| self.write_cmd(pose, &seq_dir))
+            .collect::<Result<Vec<_>>>()?;
+        job_starter
+            .submit_batch(&cmds, &work_dir, &JobOptions::default())
+            .await?;
+
         let mut new_records: Vec<PoseRecord> = Vec::new();
 
         for pose in &poses.df {
-            // Check if we need to copy input pdb to working dir? No, --pdb_path handles it.
-            let cmd = self.write_cmd(pose, &seq_dir)?;
-
-            // Run LigandMPNN
-            LocalJobStarter::run_command(&cmd, &work_dir).await?;
-
             let pdb_name = std::path::Path::new(&pose.poses_description)
                 .file_stem()
                 .unwrap()
@@ -95,6 +105,15 @@ impl Runner for LigandMPNN {
                         let scores = Self::parse_fasta_header(header);
 
                         let mut rec = pose.clone();
+                        rec.input_poses = Some(pose.poses.clone());
+                        rec.push_provenance(ProvenanceStep {
+                            stage_prefix: prefix.to_string(),
+                            runner: "LigandMPNN".to_string(),
+                            command: self.write_cmd(pose, &seq_dir).unwrap_or_default(),
+                            input_poses: Some(pose.poses.clone()),
+                            input_description: Some(pose.poses_description.clone()),
+                            timestamp: Utc::now(),
+                        });
 
                         // Check if sequence threaded PDB exists
                         let seq_name = header.split(", ").next().unwrap_or(&pdb_name);
@@ -1,12 +1,12 @@
-use crate::poses::Poses;
-use crate::runners::Runner;
+use crate::poses::{Poses, ProvenanceStep};
+use crate::runners::{JobOptions, JobStarter, Runner};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::Utc;
 use log::{info, warn};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use tokio::fs;
 
 pub struct ChainRemover {
@@ -28,7 +28,12 @@ impl ChainRemover {
 
 #[async_trait]
 impl Runner for ChainRemover {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         fs::create_dir_all(&work_dir).await?;
         let work_dir_canon = std::fs::canonicalize(&work_dir)?;
@@ -58,16 +63,9 @@ impl Runner for ChainRemover {
         );
 
         info!("Running ChainRemover: {}", cmd);
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .current_dir(&work_dir_canon)
-            .spawn()?;
-
-        let status = child.wait()?;
-        if !status.success() {
-            anyhow::bail!("ChainRemover failed");
-        }
+        job_starter
+            .submit_batch(&[cmd], &work_dir_canon, &JobOptions::default())
+            .await?;
 
         let mut new_records = Vec::new();
         for pose in &poses.df {
@@ -76,7 +74,16 @@ impl Runner for ChainRemover {
 
             if new_path.exists() {
                 let mut record = pose.clone();
+                record.input_poses = Some(pose.poses.clone());
                 record.poses = new_path.to_string_lossy().to_string();
+                record.push_provenance(ProvenanceStep {
+                    stage_prefix: prefix.to_string(),
+                    runner: "ChainRemover".to_string(),
+                    command: cmd.clone(),
+                    input_poses: Some(pose.poses.clone()),
+                    input_description: Some(pose.poses_description.clone()),
+                    timestamp: Utc::now(),
+                });
                 new_records.push(record);
             } else {
                 warn!("Expected output file not found: {:?}", new_path);
@@ -109,7 +116,12 @@ impl ChainAdder {
 
 #[async_trait]
 impl Runner for ChainAdder {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         fs::create_dir_all(&work_dir).await?;
         let work_dir_canon = std::fs::canonicalize(&work_dir)?;
@@ -169,16 +181,9 @@ impl Runner for ChainAdder {
         );
 
         info!("Running ChainAdder: {}", cmd);
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .current_dir(&work_dir_canon)
-            .spawn()?;
-
-        let status = child.wait()?;
-        if !status.success() {
-            anyhow::bail!("ChainAdder failed");
-        }
+        job_starter
+            .submit_batch(&[cmd], &work_dir_canon, &JobOptions::default())
+            .await?;
 
         let mut new_records = Vec::new();
         for pose in &poses.df {
@@ -187,7 +192,16 @@ impl Runner for ChainAdder {
 
             if new_path.exists() {
                 let mut record = pose.clone();
+                record.input_poses = Some(pose.poses.clone());
                 record.poses = new_path.to_string_lossy().to_string();
+                record.push_provenance(ProvenanceStep {
+                    stage_prefix: prefix.to_string(),
+                    runner: "ChainAdder".to_string(),
+                    command: cmd.clone(),
+                    input_poses: Some(pose.poses.clone()),
+                    input_description: Some(pose.poses_description.clone()),
+                    timestamp: Utc::now(),
+                });
                 new_records.push(record);
             } else {
                 warn!("Expected output file not found: {:?}", new_path);
@@ -1,17 +1,135 @@
-use crate::poses::{PoseRecord, Poses};
-use crate::runners::{LocalJobStarter, Runner};
+use crate::poses::{PoseRecord, Poses, ProvenanceStep};
+use crate::runners::{JobOptions, JobStarter, Runner};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::Utc;
 use log::warn;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
 use tokio::process::Command;
 
+/// Typed RFDiffusion options keyed by Hydra-style dotted paths (e.g.
+/// `inference.num_designs`, `contigmap.contigs`, `denoiser.noise_scale_ca`).
+/// Holding them in a map rather than a free-form string lets callers build
+/// configs programmatically, merge config defaults under per-pose overrides,
+/// and validate before any expensive job is spawned — replacing the old
+/// substring-sniffing on a concatenated shell line.
+#[derive(Debug, Clone, Default)]
+pub struct RFDiffusionOptions {
+    opts: HashMap<String, Value>,
+}
+
+impl RFDiffusionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the legacy whitespace-separated `key=value` form so existing
+    /// config/pipeline strings keep working.
+    pub fn parse(s: &str) -> Self {
+        let mut options = Self::default();
+        for token in s.split_whitespace() {
+            if let Some((k, v)) = token.split_once('=') {
+                // Keep numbers numeric so rendering is lossless; fall back to
+                // a string otherwise.
+                let value = v
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .or_else(|_| v.parse::<f64>().map(Value::from))
+                    .unwrap_or_else(|_| Value::from(v.to_string()));
+                options.opts.insert(k.to_string(), value);
+            }
+        }
+        options
+    }
+
+    /// Set a raw Hydra key path to any JSON value, returning `&mut self` for
+    /// chaining.
+    pub fn set(&mut self, key: &str, value: impl Into<Value>) -> &mut Self {
+        self.opts.insert(key.to_string(), value.into());
+        self
+    }
+
+    pub fn num_designs(&mut self, n: usize) -> &mut Self {
+        self.set("inference.num_designs", n as u64)
+    }
+
+    pub fn input_pdb(&mut self, path: &str) -> &mut Self {
+        self.set("inference.input_pdb", path.to_string())
+    }
+
+    pub fn output_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.set("inference.output_prefix", prefix.to_string())
+    }
+
+    pub fn contigs(&mut self, contigs: &str) -> &mut Self {
+        self.set("contigmap.contigs", contigs.to_string())
+    }
+
+    pub fn noise_scale_ca(&mut self, scale: f64) -> &mut Self {
+        self.set("denoiser.noise_scale_ca", scale)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.opts.contains_key(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opts.is_empty()
+    }
+
+    /// Merge `overrides` on top of these options; keys in `overrides` win. Used
+    /// to layer per-pose overrides over shared config defaults.
+    pub fn merge(&mut self, overrides: &RFDiffusionOptions) {
+        for (k, v) in &overrides.opts {
+            self.opts.insert(k.clone(), v.clone());
+        }
+    }
+
+    /// Validate before launching: enforce required keys and reject malformed
+    /// ones. A `HashMap` already prevents duplicate keys, so a rejected config
+    /// here is a genuinely unusable one rather than a concatenation artefact.
+    pub fn validate(&self) -> Result<()> {
+        for required in ["inference.input_pdb", "inference.output_prefix"] {
+            if !self.opts.contains_key(required) {
+                anyhow::bail!("RFDiffusion options missing required key `{}`", required);
+            }
+        }
+        for (k, v) in &self.opts {
+            if k.trim().is_empty() || k.contains(char::is_whitespace) {
+                anyhow::bail!("RFDiffusion option key `{}` is malformed", k);
+            }
+            if render_value(v).is_empty() {
+                anyhow::bail!("RFDiffusion option `{}` has an empty value", k);
+            }
+        }
+        Ok(())
+    }
+
+    /// Render the options as deterministic `key=value` command tokens.
+    pub fn to_cmd_args(&self) -> Vec<String> {
+        let mut keys: Vec<&String> = self.opts.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .map(|k| format!("{}={}", k, render_value(&self.opts[k])))
+            .collect()
+    }
+}
+
+/// Render a JSON value into the scalar form Hydra expects on the command line.
+fn render_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
 pub struct RFDiffusion {
     pub python_path: String,
     pub script_path: String,
-    pub options: String,
+    pub options: RFDiffusionOptions,
     pub num_diffusions: usize,
 }
 
@@ -20,7 +138,7 @@ impl RFDiffusion {
         Self {
             python_path: python_path.to_string(),
             script_path: script_path.to_string(),
-            options: String::new(),
+            options: RFDiffusionOptions::new(),
             num_diffusions: 1,
         }
     }
@@ -36,44 +154,71 @@ impl RFDiffusion {
         );
         let output_prefix = output_dir.join(&desc);
 
-        let mut cmd = format!(
-            "{} {} {} inference.output_prefix={}",
-            self.python_path,
-            self.script_path,
-            self.options,
-            output_prefix.to_string_lossy()
-        );
-
-        if !self.options.contains("inference.input_pdb") {
-            cmd.push_str(&format!(" inference.input_pdb={}", pose.poses));
+        // Config defaults merge *under* per-pose overrides: start from the
+        // shared options, then layer any `rfdiffusion_options` object carried
+        // on the pose.
+        let mut options = self.options.clone();
+        if let Some(Value::Object(map)) = pose.extra_fields.get("rfdiffusion_options") {
+            let mut overrides = RFDiffusionOptions::new();
+            for (k, v) in map {
+                overrides.set(k, v.clone());
+            }
+            options.merge(&overrides);
         }
 
-        if !self.options.contains("inference.num_designs") {
-            cmd.push_str(" inference.num_designs=1");
+        // The output prefix is always set per design, so the outputs can be
+        // found below; the input PDB and design count only fill gaps the
+        // caller did not set.
+        options.output_prefix(&output_prefix.to_string_lossy());
+        if !options.contains_key("inference.input_pdb") {
+            options.input_pdb(&pose.poses);
         }
+        if !options.contains_key("inference.num_designs") {
+            options.num_designs(1);
+        }
+
+        // Reject malformed/incomplete configs before spawning the job.
+        options.validate()?;
 
-        Ok(cmd)
+        let mut parts = vec![self.python_path.clone(), self.script_path.clone()];
+        parts.extend(options.to_cmd_args());
+        Ok(parts.join(" "))
     }
 }
 
 #[async_trait]
 impl Runner for RFDiffusion {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         let pdb_dir = work_dir.join("output_pdbs");
         tokio::fs::create_dir_all(&pdb_dir)
             .await
             .context("Failed to create working directories")?;
 
-        let mut new_records = Vec::new();
-
+        // Build one command per (pose, diffusion index) and dispatch the whole
+        // set through the job starter so the many designs run concurrently.
+        let mut cmds = Vec::new();
+        let mut plan: Vec<(&PoseRecord, usize)> = Vec::new();
         for pose in &poses.df {
             for i in 0..self.num_diffusions {
-                let cmd = self.write_cmd(pose, &pdb_dir, i)?;
+                cmds.push(self.write_cmd(pose, &pdb_dir, i)?);
+                plan.push((pose, i));
+            }
+        }
 
-                // Run RFDiffusion
-                LocalJobStarter::run_command(&cmd, &work_dir).await?;
+        job_starter
+            .submit_batch(&cmds, &work_dir, &JobOptions::default())
+            .await?;
 
+        let mut new_records = Vec::new();
+
+        for ((pose, i), cmd) in plan.into_iter().zip(cmds.iter()) {
+            {
                 // Parse output .trb
                 let desc = format!(
                     "{}_{:04}",
@@ -113,12 +258,22 @@ impl Runner for RFDiffusion {
                             }
                         }
 
-                        new_records.push(PoseRecord {
+                        let mut record = PoseRecord {
                             input_poses: Some(pose.poses.clone()),
                             poses: new_pdb,
                             poses_description: new_desc,
+                            provenance: pose.provenance.clone(),
                             extra_fields: extra,
+                        };
+                        record.push_provenance(ProvenanceStep {
+                            stage_prefix: prefix.to_string(),
+                            runner: "RFDiffusion".to_string(),
+                            command: cmd.clone(),
+                            input_poses: Some(pose.poses.clone()),
+                            input_description: Some(pose.poses_description.clone()),
+                            timestamp: Utc::now(),
                         });
+                        new_records.push(record);
                     } else {
                         warn!("Failed to parse TRB file: {:?}", expected_trb);
                     }
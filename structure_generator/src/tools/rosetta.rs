@@ -1,11 +1,11 @@
-use crate::poses::{PoseRecord, Poses};
-use crate::runners::Runner;
+use crate::poses::{PoseRecord, Poses, ProvenanceStep};
+use crate::runners::{JobOptions, JobStarter, Runner};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::Utc;
 use log::{info, warn};
 use serde_json::Value;
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::path::Path;
 use tokio::fs;
 
 pub struct Rosetta {
@@ -52,7 +52,12 @@ impl Rosetta {
 
 #[async_trait]
 impl Runner for Rosetta {
-    async fn run(&self, poses: &mut Poses, prefix: &str) -> Result<()> {
+    async fn run(
+        &self,
+        poses: &mut Poses,
+        prefix: &str,
+        job_starter: &dyn JobStarter,
+    ) -> Result<()> {
         let work_dir = poses.work_dir.join(prefix);
         fs::create_dir_all(&work_dir).await?;
         let work_dir_canon = std::fs::canonicalize(&work_dir)?; // Canonicalize for subprocess
@@ -62,30 +67,32 @@ impl Runner for Rosetta {
             self.application, work_dir_canon
         );
 
+        // Build one command per (pose, struct) and dispatch the whole set,
+        // then collect results in the same order they were submitted.
+        let mut cmds = Vec::new();
+        for pose in &poses.df {
+            let pose_filename = Path::new(&pose.poses).file_stem().unwrap().to_str().unwrap();
+            for n in 1..=self.nstruct {
+                cmds.push(self.construct_command(&pose.poses, &work_dir_canon, n, pose_filename));
+            }
+        }
+        // Tolerate individual failures here; a (pose, struct) whose score file
+        // is missing is skipped below without a per-pose warning.
+        if let Err(e) = job_starter
+            .submit_batch(&cmds, &work_dir_canon, &JobOptions::default())
+            .await
+        {
+            warn!("Some Rosetta tasks failed: {}", e);
+        }
+
         let mut next_poses_records = Vec::new();
 
-        // Iterate over input poses (using df field)
         for (i, pose) in poses.df.iter().enumerate() {
             let input_pdb = &pose.poses; // Field is 'poses' (path string)
             let pose_filename = Path::new(input_pdb).file_stem().unwrap().to_str().unwrap();
 
             for n in 1..=self.nstruct {
-                let cmd = self.construct_command(input_pdb, &work_dir_canon, n, pose_filename);
-
-                info!("Executing: {}", cmd);
-
-                let mut child = Command::new("sh")
-                    .arg("-c")
-                    .arg(&cmd)
-                    .current_dir(&work_dir_canon)
-                    .spawn()?;
-
-                let status = child.wait()?;
-                if !status.success() {
-                    warn!("Rosetta failed for pose {} struct {}", pose_filename, n);
-                    continue;
-                }
-
+                let _ = i;
                 let score_json_path =
                     work_dir_canon.join(format!("r{:04}_{}_score.json", n, pose_filename));
                 if score_json_path.exists() {
@@ -107,6 +114,7 @@ impl Runner for Rosetta {
                             input_poses: Some(input_pdb.clone()),
                             poses: new_path.to_string_lossy().to_string(),
                             poses_description: new_desc,
+                            provenance: pose.provenance.clone(),
                             extra_fields: pose.extra_fields.clone(), // Copy existing extra fields
                         };
 
@@ -116,6 +124,20 @@ impl Runner for Rosetta {
                             new_record.extra_fields.insert(k, val);
                         }
 
+                        new_record.push_provenance(ProvenanceStep {
+                            stage_prefix: prefix.to_string(),
+                            runner: "Rosetta".to_string(),
+                            command: self.construct_command(
+                                input_pdb,
+                                &work_dir_canon,
+                                n,
+                                pose_filename,
+                            ),
+                            input_poses: Some(input_pdb.clone()),
+                            input_description: Some(pose.poses_description.clone()),
+                            timestamp: Utc::now(),
+                        });
+
                         next_poses_records.push(new_record);
                     } else {
                         warn!("Expected output PDB not found: {:?}", old_path);